@@ -0,0 +1,52 @@
+use druid::{Data, Lens, Selector};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+pub const SET_ADJUSTMENTS: Selector<Adjustments> =
+    Selector::new("Tell the ZStack how the colors of the screenshot are adjusted, params: adjustments");
+
+/// The color adjustments of the screenshot, applied under the annotations: the default ones leave
+/// the screenshot untouched.
+#[derive(Clone, Copy, Data, Lens, PartialEq, Default, Serialize, Deserialize)]
+pub struct Adjustments {
+    /// from -100 to 100
+    pub(crate) brightness: f64,
+    /// from -100 to 100
+    pub(crate) contrast: f64,
+    /// from -100 (gray) to 100
+    pub(crate) saturation: f64,
+    pub(crate) grayscale: bool,
+    pub(crate) invert: bool,
+}
+
+impl Adjustments {
+    /// Checks if the adjustments leave the screenshot untouched.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Adjusts the colors of the image, the transparency is kept.
+    pub fn apply(&self, img: &mut DynamicImage) {
+        if self.is_identity() {
+            return;
+        }
+        let offset = (self.brightness.clamp(-100., 100.) * 2.55) as f32;
+        // the contrast grows faster than linearly, so that 100 is almost a threshold
+        let contrast = ((100. + self.contrast.clamp(-100., 100.)) / 100.).powi(2) as f32;
+        let saturation = (1. + self.saturation.clamp(-100., 100.) / 100.) as f32;
+
+        let mut rgba = img.to_rgba8();
+        for px in rgba.pixels_mut() {
+            let mut rgb = [px[0], px[1], px[2]].map(|c| (c as f32 + offset - 128.) * contrast + 128.);
+            let luma = 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+            rgb = rgb.map(|c| if self.grayscale { luma } else { luma + (c - luma) * saturation });
+            if self.invert {
+                rgb = rgb.map(|c| 255. - c);
+            }
+            for (i, c) in rgb.iter().enumerate() {
+                px[i] = c.round().clamp(0., 255.) as u8;
+            }
+        }
+        *img = DynamicImage::ImageRgba8(rgba);
+    }
+}
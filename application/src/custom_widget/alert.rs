@@ -1,4 +1,6 @@
-use druid::{Data, Lens};
+use druid::{Data, Lens, Selector};
+
+pub const SHOW_ALERT: Selector<String> = Selector::new("Show an alert message in the main window");
 
 #[derive(Clone, Data, Lens)]
 pub struct Alert {
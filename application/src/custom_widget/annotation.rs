@@ -0,0 +1,289 @@
+use druid::kurbo::common::FloatExt;
+use druid::{Affine, Color, Data, Lens, Point, Rect};
+use image::imageops::{overlay, FilterType};
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Luma, Pixel, Rgba, RgbaImage};
+use imageproc::distance_transform::Norm;
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use imageproc::morphology::dilate;
+use serde::{Deserialize, Serialize};
+use crate::custom_widget::TextStyle;
+use crate::custom_widget::callout::Callout;
+
+/// How the pixels of an annotation are mixed with the ones of the screenshot.
+#[derive(Clone, Copy, Data, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// the annotation is painted over the screenshot
+    #[default]
+    Normal,
+    /// the screenshot is tinted with the annotation color, the dark text stays readable
+    Multiply,
+    /// every channel keeps the darkest value between the screenshot and the annotation
+    Darken,
+}
+
+impl BlendMode {
+    /// Mixes the pixel of the annotation into the one of the screenshot.
+    fn blend(&self, back_px: &mut Rgba<u8>, over_px: &Rgba<u8>) {
+        let mix = |back: u8, over: u8| -> u8 {
+            match self {
+                BlendMode::Multiply => (back as u32 * over as u32 / u8::MAX as u32) as u8,
+                _ => back.min(over),
+            }
+        };
+        match self {
+            BlendMode::Normal => back_px.blend(over_px),
+            BlendMode::Multiply | BlendMode::Darken => {
+                let alpha = over_px[3] as f64 / u8::MAX as f64;
+                for c in 0..3 {
+                    let mixed = mix(back_px[c], over_px[c]) as f64;
+                    back_px[c] = (back_px[c] as f64 + (mixed - back_px[c] as f64) * alpha).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+/// opacity of the darkest part of a drop shadow
+const SHADOW_ALPHA: f64 = 0.6;
+
+/// The outline and the drop shadow drawn under an annotation, the sizes are in pixels of the
+/// screenshot.
+#[derive(Clone, Copy, Data, Lens, PartialEq)]
+pub struct AnnotationStyle {
+    pub(crate) outline: bool,
+    pub(crate) outline_color: Color,
+    pub(crate) outline_width: f64,
+    pub(crate) shadow: bool,
+    /// how far the shadow is moved to the bottom right
+    pub(crate) shadow_offset: f64,
+    /// the standard deviation of the blur, a sharp shadow with 0
+    pub(crate) shadow_blur: f64,
+}
+
+impl Default for AnnotationStyle {
+    fn default() -> Self {
+        Self {
+            outline: false,
+            outline_color: Color::WHITE,
+            outline_width: 3.,
+            shadow: false,
+            shadow_offset: 6.,
+            shadow_blur: 4.,
+        }
+    }
+}
+
+impl AnnotationStyle {
+    /// Renders the outline and the drop shadow of an image, without the image itself.
+    ///
+    /// Returns the decoration and how many pixels it extends beyond the image on every side,
+    /// `None` if there is nothing to draw.
+    pub fn decoration(&self, img: &DynamicImage) -> Option<(DynamicImage, u32)> {
+        if !self.outline && !self.shadow {
+            return None;
+        }
+        let outline = if self.outline {
+            self.outline_width.round().clamp(1., u8::MAX as f64) as u32
+        } else {
+            0
+        };
+        let (offset, blur) = if self.shadow {
+            (self.shadow_offset.round().max(0.) as u32, self.shadow_blur.max(0.))
+        } else {
+            (0, 0.)
+        };
+        // the blur spreads the shadow about three standard deviations away
+        let margin = outline + offset + (blur * 3.).ceil() as u32;
+        let (width, height) = (img.width() + 2 * margin, img.height() + 2 * margin);
+
+        let mut shape = GrayImage::new(width, height);
+        for (x, y, px) in img.pixels() {
+            shape.put_pixel(x + margin, y + margin, Luma([px[3]]));
+        }
+        if outline > 0 {
+            shape = dilate(&shape, Norm::L1, outline as u8);
+        }
+
+        let mut decoration = RgbaImage::new(width, height);
+        if self.shadow {
+            let shadow = if blur > 0. {
+                gaussian_blur_f32(&shape, blur as f32)
+            } else {
+                shape.clone()
+            };
+            for (x, y, px) in shadow.enumerate_pixels() {
+                if x + offset < width && y + offset < height && px[0] > 0 {
+                    let alpha = (px[0] as f64 * SHADOW_ALPHA).round() as u8;
+                    decoration.put_pixel(x + offset, y + offset, Rgba([0, 0, 0, alpha]));
+                }
+            }
+        }
+        if outline > 0 {
+            let (r, g, b, _) = self.outline_color.as_rgba8();
+            for (x, y, px) in shape.enumerate_pixels() {
+                if px[0] > 0 {
+                    decoration.get_pixel_mut(x, y).blend(&Rgba([r, g, b, px[0]]));
+                }
+            }
+        }
+        Some((DynamicImage::ImageRgba8(decoration), margin))
+    }
+}
+
+/// How an over-image is turned around the center of its rect: first it is mirrored, then it is
+/// rotated.
+#[derive(Clone, Copy, Data, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Transform {
+    /// clockwise, in degrees
+    pub(crate) rotation: f64,
+    pub(crate) flip_horizontal: bool,
+    pub(crate) flip_vertical: bool,
+}
+
+impl Transform {
+    pub fn is_identity(&self) -> bool {
+        self.rotation.rem_euclid(360.) == 0. && !self.flip_horizontal && !self.flip_vertical
+    }
+
+    /// The same transformation applied to the layout, around the center of the rect.
+    pub fn affine(&self, rect: Rect) -> Affine {
+        let center = rect.center().to_vec2();
+        let scale = (
+            if self.flip_horizontal { -1. } else { 1. },
+            if self.flip_vertical { -1. } else { 1. },
+        );
+        Affine::translate(center)
+            * Affine::rotate(self.rotation.to_radians())
+            * Affine::scale_non_uniform(scale.0, scale.1)
+            * Affine::translate(-center)
+    }
+
+    /// Mirrors and rotates the image, the rotated image is placed in a larger one so that the
+    /// corners are not cut.
+    ///
+    /// Returns the image and how many pixels it has grown on the left and on the top.
+    pub fn apply(&self, img: &DynamicImage) -> (DynamicImage, (u32, u32)) {
+        let mut out = img.clone();
+        if self.flip_horizontal {
+            out = out.fliph();
+        }
+        if self.flip_vertical {
+            out = out.flipv();
+        }
+        if self.rotation.rem_euclid(360.) == 0. {
+            return (out, (0, 0));
+        }
+
+        let (width, height) = out.dimensions();
+        let diagonal = (width as f64).hypot(height as f64).ceil() as u32;
+        let padding = ((diagonal - width) / 2, (diagonal - height) / 2);
+        let mut canvas = RgbaImage::new(diagonal, diagonal);
+        overlay(&mut canvas, &out.to_rgba8(), padding.0 as i64, padding.1 as i64);
+        let rotated = rotate_about_center(
+            &canvas,
+            self.rotation.to_radians() as f32,
+            Interpolation::Bilinear,
+            Rgba([0, 0, 0, 0]),
+        );
+        (DynamicImage::ImageRgba8(rotated), padding)
+    }
+}
+
+/// An over-image already placed on the screenshot.
+///
+/// It is kept apart from the back image until the export, so it can be selected again to be
+/// moved, resized, recolored or deleted.
+#[derive(Clone)]
+pub struct Annotation {
+    /// index of the over-image the annotation has been created from
+    pub(crate) over_img_index: usize,
+    /// the over-image, already colored
+    pub(crate) image: DynamicImage,
+    /// where the annotation lies, in pixels of the back image
+    pub(crate) rect: Rect,
+    /// the text and its style, only for the annotations generated from the text field
+    pub(crate) text: Option<(String, TextStyle)>,
+    /// the redaction box replaces the underlying pixels, whatever the alpha slider says
+    pub(crate) opaque: bool,
+    pub(crate) blend: BlendMode,
+    /// the enlarged area, only for the magnified insets
+    pub(crate) callout: Option<Callout>,
+    pub(crate) style: AnnotationStyle,
+    pub(crate) transform: Transform,
+}
+
+impl Annotation {
+    /// Checks if a pixel of the back image is covered by the annotation, following its rotation.
+    pub fn contains(&self, point: Point) -> bool {
+        let point = self.transform.affine(self.rect).inverse() * point;
+        self.rect.contains(point)
+    }
+
+    /// Draws the annotation on the image, scaled to fit its rect.
+    pub fn draw_on(&self, img: &mut DynamicImage) {
+        if let Some(callout) = self.callout.as_ref() {
+            callout.draw_frame(img, self.rect);
+        }
+        let over_img = self.image.resize(
+            self.rect.width().expand().max(1.) as u32,
+            self.rect.height().expand().max(1.) as u32,
+            FilterType::Nearest,
+        );
+        let (over_img, padding) = self.transform.apply(&over_img);
+        let x0 = self.rect.x0.floor() as i64 - padding.0 as i64;
+        let y0 = self.rect.y0.floor() as i64 - padding.1 as i64;
+
+        // the outline and the shadow are painted over the screenshot whatever the blend mode is
+        if let Some((decoration, margin)) = self.style.decoration(&over_img) {
+            for (i, j, over_px) in decoration.pixels() {
+                let (x, y) = (x0 - margin as i64 + i as i64, y0 - margin as i64 + j as i64);
+                if over_px[3] == 0 || x < 0 || y < 0 || !img.in_bounds(x as u32, y as u32) {
+                    continue;
+                }
+                let mut new_px = img.get_pixel(x as u32, y as u32);
+                new_px.blend(&over_px);
+                img.put_pixel(x as u32, y as u32, new_px);
+            }
+        }
+
+        for (i, j, mut over_px) in over_img.pixels() {
+            let (x, y) = (x0 + i as i64, y0 + j as i64);
+            if x < 0 || y < 0 || !img.in_bounds(x as u32, y as u32) {
+                continue;
+            }
+            let (x, y) = (x as u32, y as u32);
+            // only the pixels of the box, not the corners left empty by the rotation
+            if self.opaque && over_px.channels()[3] > 0 {
+                over_px.channels_mut()[3] = u8::MAX;
+            }
+            if over_px.channels()[3] == u8::MAX && (self.opaque || self.blend == BlendMode::Normal) {
+                img.put_pixel(x, y, over_px);
+            } else if over_px.channels()[3] != 0 {
+                let mut new_px = img.get_pixel(x, y);
+                self.blend.blend(&mut new_px, &over_px);
+                img.put_pixel(x, y, new_px);
+            }
+        }
+    }
+}
+
+/// Sets the color (if any) and the transparency (0-100) of every visible pixel of the image.
+pub fn color_over_img(img: &mut DynamicImage, color: Option<Color>, alpha: f64) {
+    for j in 0..img.height() {
+        for i in 0..img.width() {
+            let mut cur_px = img.get_pixel(i, j);
+            let ch = cur_px.channels_mut();
+            if ch[3] > 0 {
+                if let Some(color) = color {
+                    let color = color.as_rgba8();
+                    ch[0] = color.0;
+                    ch[1] = color.1;
+                    ch[2] = color.2;
+                }
+                ch[3] = ((alpha / 100.) * u8::MAX as f64) as u8;
+                img.put_pixel(i, j, cur_px);
+            }
+        }
+    }
+}
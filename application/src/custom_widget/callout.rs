@@ -0,0 +1,108 @@
+use druid::{Color, Point, Rect};
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba};
+use imageproc::drawing::{draw_hollow_rect_mut, draw_line_segment_mut};
+
+/// how much the source area is enlarged in the inset
+pub const CALLOUT_ZOOM: f64 = 2.;
+/// width of the inset border, of the source frame and of the connector line, in pixels of the
+/// screenshot
+const CALLOUT_BORDER: u32 = 3;
+/// space between the source area and the place where the inset is shown the first time
+pub const CALLOUT_MARGIN: f64 = 20.;
+
+/// A magnified copy of a part of the screenshot.
+#[derive(Clone)]
+pub struct Callout {
+    /// the enlarged area, in pixels of the screenshot
+    pub(crate) source: Rect,
+    pub(crate) color: Color,
+    /// if a line joins the source area to the inset
+    pub(crate) connector: bool,
+}
+
+fn rgba(color: Color) -> Rgba<u8> {
+    let (r, g, b, _) = color.as_rgba8();
+    Rgba([r, g, b, u8::MAX])
+}
+
+fn draw_border(img: &mut DynamicImage, rect: Rect, color: Rgba<u8>) {
+    for i in 0..CALLOUT_BORDER {
+        let width = rect.width() as i64 - 2 * i as i64;
+        let height = rect.height() as i64 - 2 * i as i64;
+        if width <= 0 || height <= 0 {
+            break;
+        }
+        draw_hollow_rect_mut(
+            img,
+            imageproc::rect::Rect::at(rect.x0 as i32 + i as i32, rect.y0 as i32 + i as i32)
+                .of_size(width as u32, height as u32),
+            color,
+        );
+    }
+}
+
+impl Callout {
+    /// Renders the inset: the source area of the image enlarged, with a border.
+    pub fn to_image(&self, img: &DynamicImage) -> DynamicImage {
+        let source = self.source.round();
+        let zoomed = img
+            .crop_imm(
+                source.x0.max(0.) as u32,
+                source.y0.max(0.) as u32,
+                source.width().max(1.) as u32,
+                source.height().max(1.) as u32,
+            )
+            .resize_exact(
+                (source.width().max(1.) * CALLOUT_ZOOM) as u32,
+                (source.height().max(1.) * CALLOUT_ZOOM) as u32,
+                FilterType::CatmullRom,
+            );
+        let mut inset = DynamicImage::ImageRgba8(zoomed.into_rgba8());
+        self.draw_inset_border(&mut inset);
+        inset
+    }
+
+    /// Draws the border of the inset again, with the color of the callout.
+    pub fn draw_inset_border(&self, inset: &mut DynamicImage) {
+        let inset_rect = Rect::new(0., 0., inset.width() as f64, inset.height() as f64);
+        draw_border(inset, inset_rect, rgba(self.color));
+    }
+
+    /// The two ends of the connector line, on the borders of the source area and of the inset.
+    pub fn connector_line(&self, inset_rect: Rect) -> (Point, Point) {
+        let clamp = |point: Point, rect: Rect| {
+            Point::new(point.x.clamp(rect.x0, rect.x1), point.y.clamp(rect.y0, rect.y1))
+        };
+        (
+            clamp(inset_rect.center(), self.source),
+            clamp(self.source.center(), inset_rect),
+        )
+    }
+
+    /// Draws the frame around the source area and the connector line, the inset is drawn on
+    /// top of them as any other over-image.
+    pub fn draw_frame(&self, img: &mut DynamicImage, inset_rect: Rect) {
+        let color = rgba(self.color);
+        draw_border(img, self.source.round(), color);
+        if self.connector {
+            let (start, end) = self.connector_line(inset_rect);
+            // the line is made thicker drawing it more times, moved by one pixel each time
+            for i in 0..CALLOUT_BORDER as i32 {
+                let offset = (i - CALLOUT_BORDER as i32 / 2) as f32;
+                draw_line_segment_mut(
+                    img,
+                    (start.x as f32 + offset, start.y as f32),
+                    (end.x as f32 + offset, end.y as f32),
+                    color,
+                );
+                draw_line_segment_mut(
+                    img,
+                    (start.x as f32, start.y as f32 + offset),
+                    (end.x as f32, end.y as f32 + offset),
+                    color,
+                );
+            }
+        }
+    }
+}
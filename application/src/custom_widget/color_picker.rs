@@ -0,0 +1,281 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use druid::im::Vector;
+use druid::kurbo::Circle;
+use druid::widget::prelude::*;
+use druid::{lens, Color, Data, Lens, LinearGradient, Point, Selector, UnitPoint};
+
+/// how many colors are remembered in the recent colors row
+const MAX_RECENT_COLORS: usize = 8;
+const KNOB_RADIUS: f64 = 5.;
+
+pub const START_EYEDROPPER: Selector = Selector::new("Tell the ZStack to sample the next clicked pixel");
+pub const COLOR_PICKED: Selector<Color> = Selector::new("A color has been sampled from the screenshot, params: color");
+
+/// The color being edited in the color picker, stored as hue (0-360), saturation and value (0-1).
+#[derive(Clone, Data, Lens)]
+pub struct ColorPickerState {
+    pub(crate) hue: f64,
+    pub(crate) saturation: f64,
+    pub(crate) value: f64,
+    pub(crate) recent: Vector<Color>,
+}
+
+impl Default for ColorPickerState {
+    fn default() -> Self {
+        Self {
+            hue: 0.,
+            saturation: 1.,
+            value: 1.,
+            recent: Vector::from(vec![
+                Color::RED,
+                Color::GREEN,
+                Color::BLUE,
+                Color::YELLOW,
+                Color::BLACK,
+                Color::WHITE,
+            ]),
+        }
+    }
+}
+
+impl ColorPickerState {
+    pub fn color(&self) -> Color {
+        hsv_to_color(self.hue, self.saturation, self.value)
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        let (hue, saturation, value) = color_to_hsv(color);
+        // the hue of a gray is undefined, the one already selected is kept
+        if saturation > 0. && value > 0. {
+            self.hue = hue;
+        }
+        self.saturation = saturation;
+        self.value = value;
+    }
+
+    /// Moves the color at the beginning of the recent colors, without duplicates.
+    pub fn add_recent(&mut self, color: Color) {
+        self.recent.retain(|recent| *recent != color);
+        self.recent.push_front(color);
+        self.recent.truncate(MAX_RECENT_COLORS);
+    }
+
+    /// Lens on a RGB channel of the color: 0 red, 1 green, 2 blue.
+    pub fn rgb_lens(channel: usize) -> impl Lens<ColorPickerState, u8> {
+        lens::Map::new(
+            move |data: &ColorPickerState| {
+                let (r, g, b, _) = data.color().as_rgba8();
+                [r, g, b][channel]
+            },
+            move |data: &mut ColorPickerState, channel_value: u8| {
+                let (r, g, b, _) = data.color().as_rgba8();
+                let mut rgb = [r, g, b];
+                rgb[channel] = channel_value;
+                data.set_color(Color::rgb8(rgb[0], rgb[1], rgb[2]));
+            },
+        )
+    }
+
+    /// Lens on the saturation or on the value, shown as percentage.
+    pub fn percent_lens(get: fn(&ColorPickerState) -> f64, put: fn(&mut ColorPickerState, f64)) -> impl Lens<ColorPickerState, f64> {
+        lens::Map::new(
+            move |data: &ColorPickerState| (get(data) * 100.).round(),
+            move |data: &mut ColorPickerState, percent: f64| put(data, (percent / 100.).clamp(0., 1.)),
+        )
+    }
+
+    pub fn hex_lens() -> impl Lens<ColorPickerState, HexColor> {
+        lens::Map::new(
+            |data: &ColorPickerState| HexColor(data.color()),
+            |data: &mut ColorPickerState, hex: HexColor| data.set_color(hex.0),
+        )
+    }
+}
+
+/// A color written as `#RRGGBB`, used by the hex input of the color picker.
+#[derive(Clone, Data)]
+pub struct HexColor(pub Color);
+
+impl Display for HexColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (r, g, b, _) = self.0.as_rgba8();
+        write!(f, "#{:02X}{:02X}{:02X}", r, g, b)
+    }
+}
+
+#[derive(Debug)]
+pub struct HexColorError;
+
+impl Display for HexColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the color must be written as #RRGGBB")
+    }
+}
+
+impl std::error::Error for HexColorError {}
+
+impl FromStr for HexColor {
+    type Err = HexColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(HexColorError);
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| HexColorError);
+        Ok(HexColor(Color::rgb8(channel(0)?, channel(2)?, channel(4)?)))
+    }
+}
+
+pub fn hsv_to_color(hue: f64, saturation: f64, value: f64) -> Color {
+    let chroma = value * saturation;
+    let sector = (hue.rem_euclid(360.)) / 60.;
+    let x = chroma * (1. - (sector % 2. - 1.).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let m = value - chroma;
+    Color::rgb(r + m, g + m, b + m)
+}
+
+pub fn color_to_hsv(color: Color) -> (f64, f64, f64) {
+    let (r, g, b, _) = color.as_rgba8();
+    let (r, g, b) = (r as f64 / 255., g as f64 / 255., b as f64 / 255.);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0. {
+        0.
+    } else if max == r {
+        60. * ((g - b) / delta).rem_euclid(6.)
+    } else if max == g {
+        60. * ((b - r) / delta + 2.)
+    } else {
+        60. * ((r - g) / delta + 4.)
+    };
+    let saturation = if max == 0. { 0. } else { delta / max };
+    (hue, saturation, max)
+}
+
+/// The square where the saturation (x axis) and the value (y axis) of the color are chosen.
+pub struct SaturationValueSquare;
+
+impl SaturationValueSquare {
+    fn update_color(ctx: &mut EventCtx, pos: Point, data: &mut ColorPickerState) {
+        let size = ctx.size();
+        data.saturation = (pos.x / size.width).clamp(0., 1.);
+        data.value = 1. - (pos.y / size.height).clamp(0., 1.);
+        ctx.request_paint();
+    }
+}
+
+impl Widget<ColorPickerState> for SaturationValueSquare {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut ColorPickerState, _env: &Env) {
+        match event {
+            Event::MouseDown(me) => {
+                ctx.set_active(true);
+                Self::update_color(ctx, me.pos, data);
+            }
+            Event::MouseMove(me) if ctx.is_active() => {
+                Self::update_color(ctx, me.pos, data);
+            }
+            Event::MouseUp(_) => {
+                ctx.set_active(false);
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, _ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &ColorPickerState, _env: &Env) {}
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &ColorPickerState, data: &ColorPickerState, _env: &Env) {
+        if !old_data.same(data) {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(&mut self, _ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &ColorPickerState, _env: &Env) -> Size {
+        bc.constrain(Size::new(180., 180.))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &ColorPickerState, _env: &Env) {
+        let rect = ctx.size().to_rect();
+        let saturation = LinearGradient::new(
+            UnitPoint::LEFT,
+            UnitPoint::RIGHT,
+            (Color::WHITE, hsv_to_color(data.hue, 1., 1.)),
+        );
+        let value = LinearGradient::new(
+            UnitPoint::TOP,
+            UnitPoint::BOTTOM,
+            (Color::BLACK.with_alpha(0.), Color::BLACK),
+        );
+        ctx.fill(rect, &saturation);
+        ctx.fill(rect, &value);
+
+        let knob = Point::new(data.saturation * rect.width(), (1. - data.value) * rect.height());
+        let knob_color = if data.value > 0.5 { Color::BLACK } else { Color::WHITE };
+        ctx.stroke(Circle::new(knob, KNOB_RADIUS), &knob_color, 2.);
+    }
+}
+
+/// The vertical bar where the hue of the color is chosen.
+pub struct HueBar;
+
+impl HueBar {
+    fn update_hue(ctx: &mut EventCtx, pos: Point, data: &mut ColorPickerState) {
+        data.hue = (pos.y / ctx.size().height).clamp(0., 1.) * 360.;
+        ctx.request_paint();
+    }
+}
+
+impl Widget<ColorPickerState> for HueBar {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut ColorPickerState, _env: &Env) {
+        match event {
+            Event::MouseDown(me) => {
+                ctx.set_active(true);
+                Self::update_hue(ctx, me.pos, data);
+            }
+            Event::MouseMove(me) if ctx.is_active() => {
+                Self::update_hue(ctx, me.pos, data);
+            }
+            Event::MouseUp(_) => {
+                ctx.set_active(false);
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, _ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &ColorPickerState, _env: &Env) {}
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &ColorPickerState, data: &ColorPickerState, _env: &Env) {
+        if !old_data.hue.same(&data.hue) {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(&mut self, _ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &ColorPickerState, _env: &Env) -> Size {
+        bc.constrain(Size::new(20., 180.))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &ColorPickerState, _env: &Env) {
+        let rect = ctx.size().to_rect();
+        let stops: Vec<Color> = (0..=6).map(|i| hsv_to_color(i as f64 * 60., 1., 1.)).collect();
+        let hues = LinearGradient::new(UnitPoint::TOP, UnitPoint::BOTTOM, stops.as_slice());
+        ctx.fill(rect, &hues);
+
+        let y = data.hue / 360. * rect.height();
+        ctx.stroke(
+            druid::kurbo::Line::new(Point::new(0., y), Point::new(rect.width(), y)),
+            &Color::BLACK,
+            2.,
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+use druid::widget::Controller;
+use druid::{Data, Env, Event, EventCtx, FileInfo, Lens, MouseButton, Selector, Widget};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use crate::custom_widget::{ScreenshotImage, UPDATE_SCREENSHOT};
+
+pub const SHOW_COMPARE: Selector = Selector::new("Open the window to compare two images");
+pub const COMPARE_BEFORE: Selector<FileInfo> = Selector::new("The older image to compare has been chosen");
+pub const COMPARE_AFTER: Selector<FileInfo> = Selector::new("The newer image to compare has been chosen");
+pub const LOAD_COMPARE_IMAGE: Selector<(CompareSide, String)> =
+    Selector::new("Tell the compare view to read an image, params: (which image, path)");
+pub const SET_COMPARE: Selector<CompareSettings> =
+    Selector::new("Tell the compare view how to show the differences, params: compare settings");
+pub const COMPARE_CHANGED: Selector<(u64, u64)> =
+    Selector::new("The differences have been counted again, params: (changed pixels, compared pixels)");
+pub const SHOW_COMPARE_ALERT: Selector<String> = Selector::new("Show an alert message in the compare window");
+
+/// space between the two images shown side by side, in pixels
+const SIDE_BY_SIDE_GAP: u32 = 16;
+/// how much the changed pixels are tinted with the highlight color, from 0 to 1
+const HIGHLIGHT_STRENGTH: f32 = 0.7;
+/// how much the unchanged pixels are faded to white in the difference mode, from 0 to 1
+const DIFFERENCE_FADE: f32 = 0.75;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CompareSide {
+    Before,
+    After,
+}
+
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CompareMode {
+    /// the older image next to the newer one with the differences highlighted
+    SideBySide,
+    /// the older image on the left of a line that can be dragged, the newer one on the right
+    Swipe,
+    /// only the newer image, faded where nothing changed
+    Difference,
+}
+
+#[derive(Clone, Copy, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct CompareSettings {
+    pub(crate) mode: CompareMode,
+    /// how much the color of a pixel can change before it is counted, from 0 to 100
+    pub(crate) tolerance: f64,
+    pub(crate) highlight: bool,
+}
+
+impl Default for CompareSettings {
+    fn default() -> Self {
+        Self {
+            mode: CompareMode::SideBySide,
+            tolerance: 5.,
+            highlight: true,
+        }
+    }
+}
+
+fn mix(px: &Rgba<u8>, color: [f32; 3], amount: f32) -> Rgba<u8> {
+    let mut out = *px;
+    for c in 0..3 {
+        out[c] = (px[c] as f32 * (1. - amount) + color[c] * amount).round() as u8;
+    }
+    out[3] = u8::MAX;
+    out
+}
+
+/// Finds the pixels of the newer image that differ from the same pixels of the older one, the
+/// pixels outside the older image are all changed.
+fn changed_pixels(before: &RgbaImage, after: &RgbaImage, tolerance: f64) -> Vec<bool> {
+    let max_diff = (tolerance.clamp(0., 100.) * 255. / 100.).round() as u8;
+    after
+        .enumerate_pixels()
+        .map(|(x, y, px)| {
+            if x >= before.width() || y >= before.height() {
+                return true;
+            }
+            px.0.iter()
+                .zip(before.get_pixel(x, y).0.iter())
+                .any(|(a, b)| a.abs_diff(*b) > max_diff)
+        })
+        .collect()
+}
+
+/// Tints the changed pixels of the newer image in red, or fades the unchanged ones in the
+/// difference mode.
+///
+/// Returns the newer image highlighted and the number of changed pixels.
+fn highlight(before: &RgbaImage, after: &RgbaImage, settings: &CompareSettings) -> (RgbaImage, u64) {
+    let changed = changed_pixels(before, after, settings.tolerance);
+    let count = changed.iter().filter(|changed| **changed).count() as u64;
+
+    let highlighted = RgbaImage::from_fn(after.width(), after.height(), |x, y| {
+        let px = after.get_pixel(x, y);
+        let is_changed = changed[(y * after.width() + x) as usize];
+        match (is_changed && settings.highlight, settings.mode) {
+            (true, _) => mix(px, [255., 0., 0.], HIGHLIGHT_STRENGTH),
+            (false, CompareMode::Difference) if !is_changed => mix(px, [255., 255., 255.], DIFFERENCE_FADE),
+            _ => *px,
+        }
+    });
+    (highlighted, count)
+}
+
+/// Draws the comparison of the older image and the highlighted newer one, the swipe line is at
+/// `swipe` (from 0 to 1) of the width.
+fn draw_comparison(before: &RgbaImage, highlighted: &RgbaImage, mode: CompareMode, swipe: f64) -> DynamicImage {
+    let out = match mode {
+        CompareMode::Difference => highlighted.clone(),
+        CompareMode::SideBySide => {
+            let width = before.width() + SIDE_BY_SIDE_GAP + highlighted.width();
+            let height = before.height().max(highlighted.height());
+            let mut out = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+            image::imageops::overlay(&mut out, before, 0, 0);
+            image::imageops::overlay(&mut out, highlighted, (before.width() + SIDE_BY_SIDE_GAP) as i64, 0);
+            out
+        }
+        CompareMode::Swipe => {
+            let split = (highlighted.width() as f64 * swipe.clamp(0., 1.)).round() as u32;
+            RgbaImage::from_fn(highlighted.width(), highlighted.height(), |x, y| {
+                if x == split || x + 1 == split {
+                    Rgba([255, 0, 0, 255])
+                } else if x < split && before.in_bounds(x, y) {
+                    *before.get_pixel(x, y)
+                } else {
+                    *highlighted.get_pixel(x, y)
+                }
+            })
+        }
+    };
+    DynamicImage::ImageRgba8(out)
+}
+
+/// Keeps the two images being compared and shows their comparison in the screenshot widget it
+/// wraps; in the swipe mode the line follows the mouse while the left button is pressed.
+pub struct CompareController {
+    before: Option<RgbaImage>,
+    after: Option<RgbaImage>,
+    settings: CompareSettings,
+    /// the newer image highlighted with the current settings, kept while the swipe line moves
+    highlighted: Option<RgbaImage>,
+    /// where the swipe line is, from 0 to 1 of the width
+    swipe: f64,
+}
+
+impl CompareController {
+    pub fn new() -> Self {
+        Self {
+            before: None,
+            after: None,
+            settings: CompareSettings::default(),
+            highlighted: None,
+            swipe: 0.5,
+        }
+    }
+
+    /// Counts and highlights the differences again, after an image or the settings changed.
+    fn refresh(&mut self, ctx: &mut EventCtx) {
+        if let (Some(before), Some(after)) = (self.before.as_ref(), self.after.as_ref()) {
+            let (highlighted, count) = highlight(before, after, &self.settings);
+            ctx.submit_command(COMPARE_CHANGED.with((count, after.width() as u64 * after.height() as u64)));
+            self.highlighted = Some(highlighted);
+            self.show(ctx);
+        }
+    }
+
+    /// Shows the comparison, only the swipe line is drawn again.
+    fn show(&self, ctx: &mut EventCtx) {
+        if let (Some(before), Some(highlighted)) = (self.before.as_ref(), self.highlighted.as_ref()) {
+            let img = draw_comparison(before, highlighted, self.settings.mode, self.swipe);
+            ctx.submit_command(UPDATE_SCREENSHOT.with(Arc::new(img)).to(ctx.widget_id()));
+        }
+    }
+}
+
+impl<T: Data> Controller<T, ScreenshotImage> for CompareController {
+    fn event(&mut self, child: &mut ScreenshotImage, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(LOAD_COMPARE_IMAGE) => {
+                let (side, path) = cmd.get_unchecked(LOAD_COMPARE_IMAGE);
+                match image::open(path) {
+                    Ok(img) => {
+                        match side {
+                            CompareSide::Before => self.before = Some(img.into_rgba8()),
+                            CompareSide::After => self.after = Some(img.into_rgba8()),
+                        }
+                        self.refresh(ctx);
+                    }
+                    Err(_) => ctx.submit_command(SHOW_COMPARE_ALERT.with(format!("The image {} can't be read!", path))),
+                }
+            }
+            Event::Command(cmd) if cmd.is(SET_COMPARE) => {
+                self.settings = *cmd.get_unchecked(SET_COMPARE);
+                self.refresh(ctx);
+            }
+            Event::MouseDown(me) | Event::MouseMove(me)
+                if self.settings.mode == CompareMode::Swipe && me.buttons.contains(MouseButton::Left) =>
+            {
+                // the image is fitted in the widget, the line follows the mouse on the image
+                let image_rect = child.painted_rect(ctx.size());
+                if image_rect.width() > 0. {
+                    self.swipe = ((me.pos.x - image_rect.x0) / image_rect.width()).clamp(0., 1.);
+                    self.show(ctx);
+                }
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
@@ -1,21 +1,105 @@
 use crate::custom_widget::screenshot_image::UPDATE_SCREENSHOT;
-use crate::custom_widget::{ResizableBox, UPDATE_ORIGIN, verify_exists_dir};
+use crate::custom_widget::{ResizableBox, TextStyle, UPDATE_ORIGIN, UPDATE_ROTATION, verify_exists_dir};
+use crate::custom_widget::text_style::text_to_image;
+use crate::custom_widget::step_marker::{draw_step_markers, StepMarker, MARKER_RADIUS};
+use crate::custom_widget::color_picker::{COLOR_PICKED, START_EYEDROPPER};
+use crate::custom_widget::annotation::{color_over_img, Annotation, AnnotationStyle, BlendMode, Transform};
+use crate::custom_widget::stroke::{stroke_to_image, StrokeShape, STROKE_WIDTH};
+use crate::custom_widget::spotlight::{apply_spotlight, SpotlightRegion, SpotlightSettings, SpotlightShape};
+use crate::custom_widget::project::{Project, LOAD_PROJECT, WRITE_PROJECT};
+use crate::custom_widget::callout::{Callout, CALLOUT_MARGIN};
+use crate::custom_widget::measurement::{
+    draw_measurements, MeasureShape, Measurement, MEASURE_LABEL_PADDING, MEASURE_LABEL_SIZE,
+};
+use crate::custom_widget::watermark::{apply_watermark, CaptureInfo, WatermarkSettings, CAPTURE_INFO, SET_WATERMARK};
+use crate::custom_widget::export::{
+    keeps_alpha, save_image, Beautify, EncodeSettings, ExportScale, SET_BEAUTIFY, SET_ENCODE, SET_EXPORT_SCALE,
+};
+use crate::custom_widget::selected_rect::SET_CROP_SELECTION;
+use crate::custom_widget::adjustments::{Adjustments, SET_ADJUSTMENTS};
+use crate::custom_widget::write_to_file;
 use druid::kurbo::common::FloatExt;
-use druid::piet::ImageFormat;
-use druid::widget::Image;
+use druid::piet::{ImageFormat, InterpolationMode, PietImage};
+use druid::widget::{Axis, Image};
+use druid::kurbo::{BezPath, Circle, Line};
+use druid::piet::{Text, TextLayout, TextLayoutBuilder};
 use druid::{
-    commands, BoxConstraints, Color, Data, Env, Event, EventCtx, ImageBuf, InternalEvent,
-    LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, Selector, Size, Target, UnitPoint,
-    UpdateCtx, Vec2, Widget, WidgetExt, WidgetId, WidgetPod,
+    commands, BoxConstraints, Color, Cursor, Data, Env, Event, EventCtx, FontFamily, ImageBuf,
+    InternalEvent, LayoutCtx, LifeCycle, LifeCycleCtx, MouseButton, MouseEvent, PaintCtx, Point,
+    Rect, RenderContext, Selector, Size, Target, UnitPoint, UpdateCtx, Vec2, Widget, WidgetExt,
+    WidgetId, WidgetPod,
 };
 use image::imageops::FilterType;
 use image::io::Reader;
-use image::{DynamicImage, GenericImage, GenericImageView, Pixel};
+use image::{DynamicImage, GenericImageView, Pixel, RgbaImage};
 use image::{ImageFormat as imgFormat, Rgba};
-use imageproc::drawing::draw_text_mut;
-use rusttype::{Font, Scale};
 use std::sync::Arc;
 use crate::BASE_PATH_SCREENSHOT;
+use crate::custom_widget::SHOW_ALERT;
+
+/// index of the highlighter icon, it tints the screenshot by default
+const HIGHLIGHTER_INDEX: usize = 3;
+/// index of the over-image generated from the text field
+const TEXT_INDEX: usize = 4;
+/// index of the redaction box, it is always drawn fully opaque
+const REDACT_INDEX: usize = 5;
+/// index of the first stamp, the stamps follow the built-in over-images
+const STAMPS_INDEX: usize = 6;
+/// the highlighter strokes are drawn by the user, they are not one of the over-images
+const STROKE_INDEX: usize = usize::MAX;
+/// the magnified insets are copies of the screenshot, they are not one of the over-images
+const CALLOUT_INDEX: usize = usize::MAX - 1;
+/// size of the longest side of a stamp when it is placed
+const STAMP_SIZE: f64 = 100.;
+/// minimum luminance gap between two neighbour pixels to be considered a glyph edge
+const TEXT_EDGE_THRESHOLD: i32 = 80;
+/// offset of a new over-image from the center of the screenshot
+const OVER_IMG_OFFSET: Vec2 = Vec2::new(5., 5.);
+/// how much a step of the mouse wheel zooms in or out
+const ZOOM_STEP: f64 = 1.25;
+/// the largest zoom, in layout points for every pixel of the screenshot
+const MAX_ZOOM: f64 = 16.;
+/// the height of the area showing the zoomed screenshot, unless the screenshot fits in less
+const ZOOM_VIEWPORT_HEIGHT: f64 = 600.;
+
+/// A change of the orientation of the whole screenshot.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Orientation {
+    /// 90° clockwise
+    RotateRight,
+    /// 90° counterclockwise
+    RotateLeft,
+    FlipHorizontal,
+    FlipVertical,
+}
+
+impl Orientation {
+    /// Where a point of an image of the given size ends up.
+    fn map_point(&self, point: Point, size: Size) -> Point {
+        match self {
+            Orientation::RotateRight => Point::new(size.height - point.y, point.x),
+            Orientation::RotateLeft => Point::new(point.y, size.width - point.x),
+            Orientation::FlipHorizontal => Point::new(size.width - point.x, point.y),
+            Orientation::FlipVertical => Point::new(point.x, size.height - point.y),
+        }
+    }
+
+    fn map_rect(&self, rect: Rect, size: Size) -> Rect {
+        Rect::from_points(
+            self.map_point(rect.origin(), size),
+            self.map_point(Point::new(rect.x1, rect.y1), size),
+        )
+    }
+
+    fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        match self {
+            Orientation::RotateRight => img.rotate90(),
+            Orientation::RotateLeft => img.rotate270(),
+            Orientation::FlipHorizontal => img.fliph(),
+            Orientation::FlipVertical => img.flipv(),
+        }
+    }
+}
 
 pub enum OverImages {
     Circles,
@@ -24,14 +108,54 @@ pub enum OverImages {
     Highlighter,
     Remove,
     Text,
+    Redact,
+    Marker,
+    Stamp(usize),
+    Stroke(StrokeShape),
+    Spotlight,
+    Callout,
+    Measure(MeasureShape),
 }
 pub const UPDATE_BACK_IMG: Selector<Arc<DynamicImage>> = Selector::new("Update the back image");
+pub const CROP_BACK_IMG: Selector<(Rect, Size)> =
+    Selector::new("Tell the ZStack the back image has been cropped, params: (the kept rect in pixels of the back image, size of the back image)");
+pub const START_CROP: Selector =
+    Selector::new("Tell the ZStack to show the whole capture, so that the crop can be changed");
+pub const CANCEL_CROP: Selector = Selector::new("Tell the ZStack to restore the crop used before changing it");
+pub const RESET_CROP: Selector = Selector::new("Tell the ZStack to edit the whole capture again");
 pub const UPDATE_COLOR: Selector<(Option<Color>, Option<f64>)> =
     Selector::new("Update the over-img color");
-pub const SHOW_OVER_IMG: Selector<(OverImages, Option<String>)> =
+pub const SHOW_OVER_IMG: Selector<(OverImages, Option<(String, TextStyle)>)> =
     Selector::new("Tell the ZStack to show the over_img, params: over_img path");
+pub const UPDATE_OVER_TEXT: Selector<(String, TextStyle)> =
+    Selector::new("Tell the ZStack to render again the showing text, params: (text, text style)");
 pub const SAVE_OVER_IMG: Selector<(Box<str>, Box<str>, image::ImageFormat)> = Selector::new("Tell the ZStack to save the modified screenshot, params: (Screenshot original img's path, Folder Path Where To Save, New File Name, Image Format)");
-pub const CREATE_ZSTACK: Selector<Vec<&'static str>> = Selector::new("Initialized the over-images");
+pub const SET_BLEND_MODE: Selector<BlendMode> =
+    Selector::new("Tell the ZStack how to blend the showing over-image, params: blend mode");
+pub const BLEND_MODE_CHANGED: Selector<BlendMode> =
+    Selector::new("The blend mode of the showing over-image has changed, params: blend mode");
+pub const SET_ANNOTATION_STYLE: Selector<AnnotationStyle> =
+    Selector::new("Tell the ZStack the outline and the shadow of the showing over-image, params: annotation style");
+pub const ANNOTATION_STYLE_CHANGED: Selector<AnnotationStyle> =
+    Selector::new("The style of the showing over-image has changed, params: annotation style");
+pub const FLIP_OVER_IMG: Selector<Axis> =
+    Selector::new("Tell the ZStack to mirror the showing over-image, params: the axis along which it is mirrored");
+pub const ORIENT_SCREENSHOT: Selector<Orientation> =
+    Selector::new("Tell the ZStack to rotate or mirror the whole screenshot, params: the new orientation");
+pub const SET_ZOOM: Selector<Option<f64>> =
+    Selector::new("Tell the ZStack how large to show the screenshot, params: layout points for every pixel, None to fit");
+pub const ZOOM_CHANGED: Selector<f64> =
+    Selector::new("The zoom of the screenshot has changed, params: layout points for every pixel");
+pub const PAN_KEY: Selector<bool> =
+    Selector::new("Tell the ZStack if the key to pan the screenshot is held, params: pressed");
+pub const SET_SPOTLIGHT: Selector<SpotlightSettings> =
+    Selector::new("Tell the ZStack how to draw the spotlight, params: spotlight settings");
+pub const SET_CALLOUT_CONNECTOR: Selector<bool> =
+    Selector::new("Tell the ZStack if the magnified insets are joined to their source, params: show the line");
+pub const ANNOTATION_SELECTED: Selector<Option<(String, TextStyle)>> =
+    Selector::new("An annotation has been selected to be edited again, params: its text and text style");
+pub const CREATE_ZSTACK: Selector<(Vec<String>, Vec<DynamicImage>)> =
+    Selector::new("Initialized the over-images, params: (icons paths, decoded stamps)");
 /// A container that stacks its children on top of each other.
 ///
 /// The container has a baselayer which has the lowest z-index and determines the size of the
@@ -39,12 +163,85 @@ pub const CREATE_ZSTACK: Selector<Vec<&'static str>> = Selector::new("Initialize
 pub struct CustomZStack<T> {
     layers: Vec<ZChild<T>>,
     back_img: Option<DynamicImage>,
+    /// the screenshot as it has been taken, the back image is the cropped part of it
+    capture: Option<DynamicImage>,
+    crop: Option<Rect>,
+    /// the colors of the back image as they are shown and exported
+    adjustments: Adjustments,
     back_img_origin: Option<Point>,
     screenshot_id: WidgetId,
     color: (Option<Color>, f64),
     over_images: Option<Vec<DynamicImage>>,
+    /// the stamps as they have been loaded, the tinted ones are in `over_images`
+    stamps: Vec<DynamicImage>,
     showing_over_img: Option<usize>,
+    over_img: Option<DynamicImage>,
+    annotations: Vec<Annotation>,
+    /// how the showing over-image is blended with the screenshot
+    blend: BlendMode,
+    /// the outline and the shadow of the showing over-image
+    style: AnnotationStyle,
+    /// how the showing over-image is mirrored and rotated
+    transform: Transform,
+    /// the outline and the shadow of the showing over-image as they are painted under it, with
+    /// the size in pixels of the back image, the style and the transform they have been rendered
+    /// for, and how much they exceed the over-image on every side
+    style_preview: Option<(Size, AnnotationStyle, Transform, Vec2, PietImage)>,
+    stroke_shape: Option<StrokeShape>,
+    /// the points of the highlighter stroke being drawn, in pixels of the back image
+    stroke: Vec<Point>,
+    spotlight: SpotlightSettings,
+    spotlight_regions: Vec<SpotlightRegion>,
+    placing_spotlight: bool,
+    /// the regions added before the current placing started, the others are dropped by Remove
+    spotlight_regions_before: usize,
+    /// where the drag of the new spotlight region started and where it is now, in pixels of the
+    /// back image
+    spotlight_drag: Option<(Point, Point)>,
+    placing_callout: bool,
+    /// where the drag selecting the area to enlarge started and where it is now, in pixels of
+    /// the back image
+    callout_drag: Option<(Point, Point)>,
+    /// the showing magnified inset
+    callout: Option<Callout>,
+    /// the showing inset as it has been cut from the screenshot, before the color adjustments
+    callout_inset: Option<DynamicImage>,
+    callout_connector: bool,
+    /// the dimension lines and boxes, drawn by the ZStack itself until the export like the step
+    /// markers
+    measurements: Vec<Measurement>,
+    measure_shape: Option<MeasureShape>,
+    /// the measurements made before the current measuring started, the others are dropped by
+    /// Remove
+    measurements_before: usize,
+    /// where the drag of the new measurement started and where it is now, in pixels of the back
+    /// image
+    measure_drag: Option<(Point, Point)>,
     text_field: Option<String>,
+    text_style: TextStyle,
+    markers: Vec<StepMarker>,
+    placing_markers: bool,
+    /// the markers placed before the current placing started, the others are dropped by Remove
+    markers_before: usize,
+    picking_color: bool,
+    /// applied to the exported image only
+    watermark: WatermarkSettings,
+    capture_info: CaptureInfo,
+    export_scale: ExportScale,
+    beautify: Beautify,
+    encode: EncodeSettings,
+    /// the crop used before the user started to change it, in pixels of the capture
+    crop_before: Option<Rect>,
+    /// layout points for every pixel of the back image, `None` when the screenshot fits the
+    /// available width
+    zoom: Option<f64>,
+    /// how much the zoomed screenshot is scrolled, in layout points
+    pan: Vec2,
+    /// the size of the screenshot when it fits the available width
+    fit_size: Size,
+    /// the last mouse position while the screenshot is dragged
+    panning: Option<Point>,
+    pan_key: bool,
 }
 
 struct ZChild<T> {
@@ -69,12 +266,54 @@ impl<T: Data> CustomZStack<T> {
                 offset: Vec2::ZERO,
             }],
             back_img: None,
+            capture: None,
+            crop: None,
+            adjustments: Adjustments::default(),
             back_img_origin: None,
             screenshot_id,
             color: (None, 100.),
             over_images: None,
+            stamps: Vec::new(),
             showing_over_img: None,
+            over_img: None,
+            annotations: Vec::new(),
+            blend: BlendMode::Normal,
+            style: AnnotationStyle::default(),
+            transform: Transform::default(),
+            style_preview: None,
+            stroke_shape: None,
+            stroke: Vec::new(),
+            spotlight: SpotlightSettings::default(),
+            spotlight_regions: Vec::new(),
+            placing_spotlight: false,
+            spotlight_regions_before: 0,
+            spotlight_drag: None,
+            placing_callout: false,
+            callout_drag: None,
+            callout: None,
+            callout_inset: None,
+            callout_connector: true,
+            measurements: Vec::new(),
+            measure_shape: None,
+            measurements_before: 0,
+            measure_drag: None,
             text_field: None,
+            text_style: TextStyle::default(),
+            markers: Vec::new(),
+            placing_markers: false,
+            markers_before: 0,
+            picking_color: false,
+            watermark: WatermarkSettings::default(),
+            capture_info: CaptureInfo::now(None),
+            export_scale: ExportScale::default(),
+            beautify: Beautify::default(),
+            encode: EncodeSettings::default(),
+            crop_before: None,
+            zoom: None,
+            pan: Vec2::ZERO,
+            fit_size: Size::ZERO,
+            panning: None,
+            pan_key: false,
         }
     }
 
@@ -83,6 +322,11 @@ impl<T: Data> CustomZStack<T> {
             self.rm_child();
         }
         self.showing_over_img = None;
+        self.over_img = None;
+        self.callout = None;
+        self.callout_inset = None;
+        self.transform = Transform::default();
+        self.style_preview = None;
         self.back_img_origin = None;
     }
 
@@ -135,157 +379,878 @@ impl<T: Data> CustomZStack<T> {
 
     pub fn show_over_img(self: &mut Self, over_img_index: usize, id: WidgetId) {
         if self.showing_over_img.is_none() {
-            let mut image = None;
-            if image == None {} // for the warning
+            let img = if self.text_field != None && over_img_index == TEXT_INDEX {
+                text_to_image(self.text_field.as_ref().unwrap().as_str(), self.color.0, &self.text_style)
+            } else {
+                self.over_images.as_ref().unwrap()[over_img_index].clone()
+            };
 
-            if self.text_field != None && over_img_index == 4 {
-                let image_modified = text_to_image(self.text_field.as_mut().unwrap().as_str(), self.color.0);
-                let over_images_cloned = self.over_images.as_mut().unwrap();
+            // the text is shown as large as it has been rendered, the icons have a default size and
+            // the stamps keep their proportions
+            let size = if over_img_index == TEXT_INDEX {
+                Size::new(img.width() as f64, img.height() as f64)
+            } else if over_img_index >= STAMPS_INDEX {
+                let scale = STAMP_SIZE / img.width().max(img.height()).max(1) as f64;
+                Size::new(img.width() as f64 * scale, img.height() as f64 * scale)
+            } else {
+                Size::new(50., 50.)
+            };
 
-                if over_images_cloned.len() > over_img_index {
-                    over_images_cloned[over_img_index] = image_modified.clone();
-                } else {
-                    over_images_cloned.push(image_modified.clone());
+            self.over_img = Some(img);
+            self.showing_over_img = Some(over_img_index);
+            self.blend = if over_img_index == HIGHLIGHTER_INDEX {
+                BlendMode::Multiply
+            } else {
+                BlendMode::Normal
+            };
+            self.add_over_img_layer(size, id);
+        } else {
+            self.rm_over_img();
+        }
+    }
+
+    /// Adds the showing over-image above the screenshot, inside a box that can be moved and resized.
+    fn add_over_img_layer(&mut self, size: Size, id: WidgetId) {
+        let mut img = self.over_img.clone().unwrap();
+        if self.blend != BlendMode::Normal {
+            // the tinting over-images are shown half transparent, to see what is under them
+            // while they are placed
+            if let Some(rgba) = img.as_mut_rgba8() {
+                rgba.pixels_mut().for_each(|px| px[3] /= 2);
+            }
+        }
+        let over_image = ResizableBox::new(
+            Image::new(ImageBuf::from_raw(
+                Arc::<[u8]>::from(img.as_bytes()),
+                ImageFormat::RgbaSeparate,
+                img.width() as usize,
+                img.height() as usize,
+            )),
+            id
+        )
+        .with_origin(self.back_img_origin.unwrap_or(Point::ZERO))
+        .with_transform(self.transform)
+        .height(size.height)
+        .width(size.width);
+        self.with_child(
+            over_image,
+            Vec2::new(1., 1.),
+            Vec2::ZERO,
+            UnitPoint::CENTER,
+            OVER_IMG_OFFSET,
+        );
+    }
+
+    /// Shows again the over-image with its top-left corner in `origin`, a point of the layout.
+    fn place_over_img(&mut self, origin: Point, size: Size, id: WidgetId) {
+        let base_rect = self.base_rect();
+        // the origin of the over-image is kept relative to the screenshot, that can be scrolled
+        let centered = UnitPoint::CENTER.resolve((base_rect.size() - size).to_rect())
+            + OVER_IMG_OFFSET
+            + base_rect.origin().to_vec2();
+        while self.layers.len() > 1 {
+            self.rm_child();
+        }
+        self.style_preview = None;
+        self.back_img_origin = Some((origin - centered).to_point());
+        self.add_over_img_layer(size, id);
+    }
+
+    /// Renders the showing over-image again, after its color or its text has changed, keeping it
+    /// where it has been moved.
+    fn refresh_over_img(&mut self, id: WidgetId) {
+        let index = match self.showing_over_img {
+            Some(index) if self.layers.len() > 1 => index,
+            _ => return,
+        };
+        let over_img_rect = self.layers[0].child.layout_rect();
+        let size = if index == TEXT_INDEX {
+            let img = text_to_image(self.text_field.as_ref().unwrap().as_str(), self.color.0, &self.text_style);
+            let size = Size::new(img.width() as f64, img.height() as f64);
+            self.over_img = Some(img);
+            size
+        } else if index == CALLOUT_INDEX {
+            // the inset is a copy of the screenshot, only its border takes the color
+            if let Some(callout) = self.callout.as_mut() {
+                callout.color = self.color.0.unwrap_or(callout.color);
+            }
+            if let (Some(callout), Some(inset)) = (self.callout.as_ref(), self.callout_inset.as_ref()) {
+                self.over_img = Some(self.adjusted_inset(inset, callout));
+            }
+            over_img_rect.size()
+        } else {
+            let alpha = if index == REDACT_INDEX { 100. } else { self.color.1 };
+            color_over_img(self.over_img.as_mut().unwrap(), self.color.0, alpha);
+            over_img_rect.size()
+        };
+        self.place_over_img(over_img_rect.origin(), size, id);
+    }
+
+    /// The showing over-image as it would be placed on the screenshot.
+    fn showing_annotation(&self) -> Option<Annotation> {
+        if self.layers.len() <= 1 {
+            return None;
+        }
+        let index = self.showing_over_img?;
+        let text = match (&self.text_field, index == TEXT_INDEX) {
+            (Some(text), true) => Some((text.clone(), self.text_style.clone())),
+            _ => None,
+        };
+        // the inset is kept without the adjustments, they are applied again at every export
+        let image = match (index == CALLOUT_INDEX, self.callout_inset.as_ref()) {
+            (true, Some(inset)) => inset.clone(),
+            _ => self.over_img.clone()?,
+        };
+        Some(Annotation {
+            over_img_index: index,
+            image,
+            rect: self.to_back_img_rect(self.layers[0].child.layout_rect()),
+            text,
+            opaque: index == REDACT_INDEX,
+            blend: self.blend,
+            callout: if index == CALLOUT_INDEX { self.callout.clone() } else { None },
+            style: self.style,
+            transform: self.transform,
+        })
+    }
+
+    /// Turns the showing over-image into an annotation of the screenshot.
+    fn commit_over_img(&mut self) {
+        if let Some(annotation) = self.showing_annotation() {
+            self.annotations.push(annotation);
+        }
+        self.rm_over_img();
+    }
+
+    /// Sets the part of the capture being edited, in pixels of the capture: the annotations and the
+    /// step markers are moved to stay on the same pixels.
+    fn set_crop(&mut self, crop: Option<Rect>) {
+        let capture = match self.capture.as_ref() {
+            Some(capture) => capture,
+            None => return,
+        };
+        let capture_rect = Rect::new(0., 0., capture.width() as f64, capture.height() as f64);
+        let crop = crop.map(|crop| crop.intersect(capture_rect)).filter(|crop| crop.area() > 0.);
+        let old_origin = self.crop.map(|crop| crop.origin()).unwrap_or(Point::ZERO);
+        let new_origin = crop.map(|crop| crop.origin()).unwrap_or(Point::ZERO);
+        let shift = old_origin - new_origin;
+
+        self.back_img = Some(match crop {
+            Some(crop) => capture.crop_imm(
+                crop.x0 as u32,
+                crop.y0 as u32,
+                crop.width() as u32,
+                crop.height() as u32,
+            ),
+            None => capture.clone(),
+        });
+        self.crop = crop;
+        for annotation in self.annotations.iter_mut() {
+            annotation.rect = annotation.rect + shift;
+            if let Some(callout) = annotation.callout.as_mut() {
+                callout.source = callout.source + shift;
+            }
+        }
+        for marker in self.markers.iter_mut() {
+            marker.center += shift;
+        }
+        for measurement in self.measurements.iter_mut() {
+            measurement.start += shift;
+            measurement.end += shift;
+        }
+        for region in self.spotlight_regions.iter_mut() {
+            region.rect = region.rect + shift;
+        }
+    }
+
+    /// Rotates or mirrors the capture together with everything drawn on it, the over-image being
+    /// edited is kept as an annotation first.
+    fn orient(&mut self, orientation: Orientation) {
+        if self.capture.is_none() || self.back_img.is_none() {
+            return;
+        }
+        self.commit_over_img();
+        self.placing_markers = false;
+        self.placing_spotlight = false;
+        self.placing_callout = false;
+        self.measure_shape = None;
+
+        let capture = self.capture.take().unwrap();
+        let back_img = self.back_img.take().unwrap();
+        let capture_size = Size::new(capture.width() as f64, capture.height() as f64);
+        let size = Size::new(back_img.width() as f64, back_img.height() as f64);
+        self.crop = self.crop.map(|crop| orientation.map_rect(crop, capture_size));
+        self.capture = Some(orientation.apply(&capture));
+        self.back_img = Some(orientation.apply(&back_img));
+
+        for annotation in self.annotations.iter_mut() {
+            // the annotation keeps its size and turns around its center, as if it had been
+            // rotated or mirrored by the user
+            let center = orientation.map_point(annotation.rect.center(), size);
+            annotation.rect = Rect::from_center_size(center, annotation.rect.size());
+            match orientation {
+                Orientation::RotateRight => annotation.transform.rotation += 90.,
+                Orientation::RotateLeft => annotation.transform.rotation -= 90.,
+                Orientation::FlipHorizontal => {
+                    annotation.transform.flip_horizontal = !annotation.transform.flip_horizontal;
+                    annotation.transform.rotation = -annotation.transform.rotation;
+                }
+                Orientation::FlipVertical => {
+                    annotation.transform.flip_vertical = !annotation.transform.flip_vertical;
+                    annotation.transform.rotation = -annotation.transform.rotation;
                 }
+            }
+            annotation.transform.rotation = annotation.transform.rotation.rem_euclid(360.);
+            if let Some(callout) = annotation.callout.as_mut() {
+                callout.source = orientation.map_rect(callout.source, size);
+            }
+        }
+        for marker in self.markers.iter_mut() {
+            marker.center = orientation.map_point(marker.center, size);
+        }
+        for measurement in self.measurements.iter_mut() {
+            measurement.start = orientation.map_point(measurement.start, size);
+            measurement.end = orientation.map_point(measurement.end, size);
+        }
+        for region in self.spotlight_regions.iter_mut() {
+            region.rect = orientation.map_rect(region.rect, size);
+        }
+    }
 
-                image = Some(image_modified);
-            } else {
-                image = Some(
-                    self.over_images
-                        .as_mut()
-                        .unwrap()
-                        .get_mut(over_img_index)
-                        .unwrap()
-                        .clone(),
-                );
+    /// Sets the color of the over-images, the redaction box ignores the transparency slider.
+    ///
+    /// The stamps are tinted starting from their own colors, so that they come back when no
+    /// color is selected.
+    fn color_over_images(&mut self) {
+        let (color, alpha) = self.color;
+        if let Some(over_images) = self.over_images.as_mut() {
+            for (index, img) in over_images.iter_mut().enumerate() {
+                let alpha = if index == REDACT_INDEX { 100. } else { alpha };
+                if index >= STAMPS_INDEX {
+                    *img = self.stamps[index - STAMPS_INDEX].clone();
+                }
+                color_over_img(img, color, alpha);
             }
+        }
+    }
 
-            let img = image.unwrap();
+    /// Selects the annotation under the point of the layout, if any: it is removed from the
+    /// annotations and shown again as the over-image, so that it can be edited.
+    fn select_annotation(&mut self, pos: Point, id: WidgetId) -> Option<Annotation> {
+        let point = self.to_back_img_point(pos)?;
+        let index = self.annotations.iter().rposition(|annotation| annotation.contains(point))?;
+        let annotation = self.annotations.remove(index);
 
-            let over_image = ResizableBox::new(
-                Image::new(ImageBuf::from_raw(
-                    Arc::<[u8]>::from(img.as_bytes()),
-                    ImageFormat::RgbaSeparate,
-                    img.width() as usize,
-                    img.height() as usize,
-                )),
-                id
-            )
-            .height(50.)
-            .width(50.);
-            self.with_child(
-                over_image,
-                Vec2::new(1., 1.),
-                Vec2::ZERO,
-                UnitPoint::CENTER,
-                Vec2::new(5., 5.),
-            )
-            .showing_over_img = Some(over_img_index);
+        let scale = self.layout_scale();
+        let size = Size::new(annotation.rect.width() * scale.0, annotation.rect.height() * scale.1);
+        let origin = self.to_layout_point(annotation.rect.origin());
+        if let Some((text, text_style)) = annotation.text.clone() {
+            self.text_field = Some(text);
+            self.text_style = text_style;
+        }
+        self.showing_over_img = Some(annotation.over_img_index);
+        self.over_img = Some(match annotation.callout.as_ref() {
+            Some(callout) => self.adjusted_inset(&annotation.image, callout),
+            None => annotation.image.clone(),
+        });
+        self.blend = annotation.blend;
+        self.style = annotation.style;
+        self.transform = annotation.transform;
+        self.callout = annotation.callout.clone();
+        self.callout_inset = annotation.callout.as_ref().map(|_| annotation.image.clone());
+        self.place_over_img(origin, size, id);
+        Some(annotation)
+    }
+
+    /// Draws the spotlight and every annotation on a copy of the back image with its colors
+    /// adjusted, the step markers excluded.
+    fn compose(&self) -> Option<DynamicImage> {
+        let mut out = self.adjusted_back_img()?;
+        apply_spotlight(&mut out, &self.spotlight_regions, &self.spotlight);
+        for annotation in self.annotations.iter() {
+            match annotation.callout.as_ref() {
+                // the insets are copies of the screenshot: they take the current adjustments
+                Some(callout) => Annotation {
+                    image: self.adjusted_inset(&annotation.image, callout),
+                    ..annotation.clone()
+                }
+                .draw_on(&mut out),
+                None => annotation.draw_on(&mut out),
+            }
+        }
+        Some(out)
+    }
+
+    /// The inset with the color adjustments of the screenshot, its border keeps the color of
+    /// the callout.
+    fn adjusted_inset(&self, inset: &DynamicImage, callout: &Callout) -> DynamicImage {
+        let mut out = inset.clone();
+        self.adjustments.apply(&mut out);
+        callout.draw_inset_border(&mut out);
+        out
+    }
+
+    /// The back image with the color adjustments, as it is shown under the annotations.
+    fn adjusted_back_img(&self) -> Option<DynamicImage> {
+        let mut out = self.back_img.clone()?;
+        self.adjustments.apply(&mut out);
+        Some(out)
+    }
+
+    /// Shows the composed image in the screenshot widget.
+    fn update_screenshot(&self, ctx: &mut EventCtx) {
+        if let Some(new_img) = self.compose() {
+            ctx.submit_command(
+                UPDATE_SCREENSHOT
+                    .with(Arc::new(new_img))
+                    .to(Target::Widget(self.screenshot_id)),
+            );
+        }
+    }
+
+    /// Adds a spotlight region where the user dragged, or removes the clicked one with the right
+    /// button.
+    fn edit_spotlight(&mut self, me: &MouseEvent) {
+        if let Some(point) = self.to_back_img_point(me.pos) {
+            if me.button == MouseButton::Right {
+                if let Some(index) = self.spotlight_regions.iter().rposition(|region| region.contains(point)) {
+                    self.spotlight_regions.remove(index);
+                    if index < self.spotlight_regions_before {
+                        self.spotlight_regions_before -= 1;
+                    }
+                }
+            } else if me.button == MouseButton::Left {
+                self.spotlight_drag = Some((point, point));
+            }
+        }
+    }
+
+    fn finish_spotlight_region(&mut self) {
+        if let Some((start, end)) = self.spotlight_drag.take() {
+            let rect = Rect::from_points(start, end);
+            // a simple click does not make a region
+            if rect.width() > 2. && rect.height() > 2. {
+                self.spotlight_regions.push(SpotlightRegion {
+                    rect,
+                    shape: self.spotlight.shape,
+                });
+            }
+        }
+    }
+
+    fn paint_spotlight_drag(&self, ctx: &mut PaintCtx) {
+        if let Some((start, end)) = self.spotlight_drag {
+            let rect = Rect::from_points(self.to_layout_point(start), self.to_layout_point(end));
+            match self.spotlight.shape {
+                SpotlightShape::Rectangle => ctx.stroke(rect, &Color::WHITE, 2.),
+                SpotlightShape::Ellipse => ctx.stroke(druid::kurbo::Ellipse::from_rect(rect), &Color::WHITE, 2.),
+            }
+        }
+    }
+
+    /// Turns the area the user dragged over into a magnified inset, shown next to it as the
+    /// over-image.
+    fn finish_callout(&mut self, id: WidgetId) {
+        let (start, end) = match self.callout_drag.take() {
+            Some(drag) => drag,
+            None => return,
+        };
+        let back_img = match self.back_img.as_ref() {
+            Some(back_img) => back_img,
+            None => return,
+        };
+        let source = Rect::from_points(start, end).round();
+        // a simple click does not select an area
+        if source.width() < 4. || source.height() < 4. {
+            return;
+        }
+        let callout = Callout {
+            source,
+            color: self.color.0.unwrap_or(Color::RED),
+            connector: self.callout_connector,
+        };
+        let img = callout.to_image(back_img);
+
+        // the inset is shown on the right of the area, or on its left if there is no room
+        let (width, height) = (img.width() as f64, img.height() as f64);
+        let x = if source.x1 + CALLOUT_MARGIN + width <= back_img.width() as f64 {
+            source.x1 + CALLOUT_MARGIN
         } else {
-            self.rm_over_img();
+            (source.x0 - CALLOUT_MARGIN - width).max(0.)
+        };
+        let y = source.y0.min(back_img.height() as f64 - height).max(0.);
+
+        let scale = self.layout_scale();
+        let size = Size::new(width * scale.0, height * scale.1);
+        let origin = self.to_layout_point(Point::new(x, y));
+        self.over_img = Some(self.adjusted_inset(&img, &callout));
+        self.callout_inset = Some(img);
+        self.showing_over_img = Some(CALLOUT_INDEX);
+        self.blend = BlendMode::Normal;
+        self.callout = Some(callout);
+        self.placing_callout = false;
+        self.place_over_img(origin, size, id);
+    }
+
+    /// Paints the area being selected, or the frame and the connector line of the showing inset.
+    fn paint_callout(&self, ctx: &mut PaintCtx) {
+        if let Some((start, end)) = self.callout_drag {
+            let rect = Rect::from_points(self.to_layout_point(start), self.to_layout_point(end));
+            ctx.stroke(rect, &self.color.0.unwrap_or(Color::RED), 2.);
+        }
+        if let (Some(callout), true) = (self.callout.as_ref(), self.layers.len() > 1) {
+            let source = Rect::from_points(
+                self.to_layout_point(callout.source.origin()),
+                self.to_layout_point(Point::new(callout.source.x1, callout.source.y1)),
+            );
+            ctx.stroke(source, &callout.color, 2.);
+            if callout.connector {
+                let inset_rect = self.to_back_img_rect(self.layers[0].child.layout_rect());
+                let (start, end) = callout.connector_line(inset_rect);
+                ctx.stroke(
+                    Line::new(self.to_layout_point(start), self.to_layout_point(end)),
+                    &callout.color,
+                    2.,
+                );
+            }
+        }
+    }
+
+    /// Adds a measurement where the user starts dragging, or removes the clicked one with the
+    /// right button.
+    fn edit_measurements(&mut self, me: &MouseEvent) {
+        if let Some(point) = self.to_back_img_point(me.pos) {
+            if me.button == MouseButton::Right {
+                if let Some(index) = self.measurements.iter().rposition(|measurement| measurement.contains(point)) {
+                    self.measurements.remove(index);
+                    if index < self.measurements_before {
+                        self.measurements_before -= 1;
+                    }
+                }
+            } else if me.button == MouseButton::Left {
+                self.measure_drag = Some((point, point));
+            }
+        }
+    }
+
+    fn finish_measurement(&mut self) {
+        if let (Some((start, end)), Some(shape)) = (self.measure_drag.take(), self.measure_shape) {
+            // a simple click does not measure anything
+            if start.distance(end) > 2. {
+                let color = self.color.0.unwrap_or(Color::RED);
+                self.measurements.push(Measurement::new(start, end, shape, color));
+            }
+        }
+    }
+
+    /// Paints the measurements and the one being dragged, their labels always show pixels of the
+    /// back image.
+    fn paint_measurements(&self, ctx: &mut PaintCtx) {
+        let dragging = match (self.measure_drag, self.measure_shape) {
+            (Some((start, end)), Some(shape)) => {
+                Some(Measurement::new(start, end, shape, self.color.0.unwrap_or(Color::RED)))
+            }
+            _ => None,
+        };
+        let scale = self.layout_scale();
+        let font_size = MEASURE_LABEL_SIZE * scale.0.min(scale.1);
+        let padding = MEASURE_LABEL_PADDING * scale.0.min(scale.1);
+        for measurement in self.measurements.iter().chain(dragging.iter()) {
+            for (start, end) in measurement.segments() {
+                ctx.stroke(
+                    Line::new(self.to_layout_point(start), self.to_layout_point(end)),
+                    &measurement.color,
+                    2.,
+                );
+            }
+
+            let text = ctx
+                .text()
+                .new_text_layout(measurement.label())
+                .font(FontFamily::SANS_SERIF, font_size)
+                .text_color(measurement.text_color())
+                .build()
+                .unwrap();
+            let text_size = text.size();
+            let anchor = self.to_layout_point(measurement.label_anchor());
+            let text_origin = Point::new(anchor.x - text_size.width / 2., anchor.y + padding);
+            let background = Rect::from_origin_size(text_origin, text_size).inflate(padding, padding);
+            ctx.fill(background, &measurement.color);
+            ctx.draw_text(&text, text_origin);
         }
     }
 
+    /// The image as it is exported: the back image with the annotations, the measurements and the
+    /// step markers, resized to the export size, with the watermark and framed by the beautify
+    /// style.
+    ///
+    /// Returns an error if the watermark can't be applied.
+    fn export_img(&self) -> Result<Option<DynamicImage>, String> {
+        let mut out = match self.compose() {
+            Some(out) => out,
+            None => return Ok(None),
+        };
+        draw_measurements(&mut out, &self.measurements);
+        draw_step_markers(&mut out, &self.markers);
+        // the watermark is applied last, so that its size doesn't change with the export size
+        let mut out = self.export_scale.apply(out);
+        apply_watermark(&mut out, &self.watermark, &self.capture_info)?;
+        Ok(Some(self.beautify.apply(out)))
+    }
+
+    /// Checks if the showing over-image or a placed annotation is semi-transparent where it lies
+    /// on something that looks like text, in that case the text could still be readable after
+    /// the export.
+    fn semi_transparent_over_text(&self) -> bool {
+        let back_img = match self.back_img.as_ref() {
+            Some(back_img) => back_img,
+            None => return false,
+        };
+        // the over-images with their position on the back image
+        let mut shapes = Vec::<(DynamicImage, i64, i64)>::new();
+        if self.layers.len() > 1
+            && self.showing_over_img.is_some()
+            && self.showing_over_img != Some(REDACT_INDEX)
+            && self.blend == BlendMode::Normal
+        {
+            if let Some(over_img) = self.over_img.as_ref() {
+                let over_img_rect = self.to_back_img_rect(self.layers[0].child.layout_rect());
+                shapes.push((
+                    over_img.resize(
+                        over_img_rect.width().expand() as u32,
+                        over_img_rect.height().expand() as u32,
+                        FilterType::Nearest,
+                    ),
+                    over_img_rect.x0.floor() as i64,
+                    over_img_rect.y0.floor() as i64,
+                ));
+            }
+        }
+        for annotation in self
+            .annotations
+            .iter()
+            .filter(|annotation| annotation.blend == BlendMode::Normal && !annotation.opaque)
+        {
+            let over_img = annotation.image.resize(
+                annotation.rect.width().expand().max(1.) as u32,
+                annotation.rect.height().expand().max(1.) as u32,
+                FilterType::Nearest,
+            );
+            let (over_img, padding) = annotation.transform.apply(&over_img);
+            shapes.push((
+                over_img,
+                annotation.rect.x0.floor() as i64 - padding.0 as i64,
+                annotation.rect.y0.floor() as i64 - padding.1 as i64,
+            ));
+        }
+
+        let luma = back_img.to_luma8();
+        shapes.iter().any(|(over_img, x0, y0)| {
+            let mut covered: u32 = 0;
+            let mut edges: u32 = 0;
+            for (i, j, px) in over_img.pixels() {
+                let (x, y) = (x0 + i as i64, y0 + j as i64);
+                let alpha = px.channels()[3];
+                if alpha == 0
+                    || alpha == u8::MAX
+                    || x < 0
+                    || y < 0
+                    || x + 1 >= luma.width() as i64
+                    || y >= luma.height() as i64
+                {
+                    continue;
+                }
+                covered += 1;
+                let (x, y) = (x as u32, y as u32);
+                let diff = luma.get_pixel(x, y).0[0] as i32 - luma.get_pixel(x + 1, y).0[0] as i32;
+                if diff.abs() > TEXT_EDGE_THRESHOLD {
+                    edges += 1;
+                }
+            }
+            // text has a lot of sharp edges: more than 2% of the covered pixels
+            covered > 0 && edges * 50 > covered
+        })
+    }
+
+    /// Saves the exported image and returns the one to show, nothing is saved if the watermark
+    /// can't be applied.
     pub fn save_new_img(
         self: &mut Self,
         new_img_path: &String,
         img_format: imgFormat,
-    ) -> Option<DynamicImage> {
-        if self.layers.len() > 1 && self.showing_over_img.is_some() {
-            let back_img = self.back_img.as_mut().unwrap();
-            let back_img_resolution = Size::new(back_img.width() as f64, back_img.height() as f64);
-            let mut back_img_rect: Rect = self.layers.get(1).unwrap().child.layout_rect();
-            let scale_factor_x = (back_img_resolution.width / back_img_rect.x1).expand();
-            let scale_factor_y = (back_img_resolution.height / back_img_rect.y1).expand();
-
-            back_img_rect.x0 = (back_img_rect.x0).floor();
-            back_img_rect.y0 = (back_img_rect.y0).floor();
-            back_img_rect.x1 = (back_img_rect.x1 * scale_factor_x).expand();
-            back_img_rect.y1 = (back_img_rect.y1 * scale_factor_y).expand();
-            let back_img = back_img.resize(
-                back_img_rect.width() as u32,
-                back_img_rect.height() as u32,
-                FilterType::Lanczos3,
+    ) -> Result<Option<DynamicImage>, String> {
+        self.commit_over_img();
+        self.placing_markers = false;
+        self.placing_spotlight = false;
+        self.placing_callout = false;
+        self.measure_shape = None;
+
+        let out = match self.export_img()? {
+            Some(out) => out,
+            None => return Ok(None),
+        };
+        save_image(&out, new_img_path, img_format, &self.encode)?;
+
+        // the step markers are painted by the ZStack itself, they are not part of the shown image
+        Ok(self.compose())
+    }
+
+    /// Converts a point of the ZStack layout into a pixel of the back image.
+    ///
+    /// Returns `None` if the point is outside the screenshot.
+    fn to_back_img_point(&self, pos: Point) -> Option<Point> {
+        let back_img = self.back_img.as_ref()?;
+        let base_rect = self.base_rect();
+        if !base_rect.contains(pos) || base_rect.width() <= 0. || base_rect.height() <= 0. {
+            return None;
+        }
+        Some(Point::new(
+            (pos.x - base_rect.x0) * back_img.width() as f64 / base_rect.width(),
+            (pos.y - base_rect.y0) * back_img.height() as f64 / base_rect.height(),
+        ))
+    }
+
+    /// Converts a rect of the ZStack layout into pixels of the back image, it can lie partially
+    /// outside the screenshot.
+    fn to_back_img_rect(&self, rect: Rect) -> Rect {
+        let base_rect = self.base_rect();
+        let scale = self.layout_scale();
+        Rect::new(
+            (rect.x0 - base_rect.x0) / scale.0,
+            (rect.y0 - base_rect.y0) / scale.1,
+            (rect.x1 - base_rect.x0) / scale.0,
+            (rect.y1 - base_rect.y0) / scale.1,
+        )
+    }
+
+    /// Converts a pixel of the back image into a point of the ZStack layout.
+    fn to_layout_point(&self, point: Point) -> Point {
+        let base_rect = self.base_rect();
+        let scale = self.layout_scale();
+        Point::new(base_rect.x0 + point.x * scale.0, base_rect.y0 + point.y * scale.1)
+    }
+
+    /// How many layout points are used to show a pixel of the back image, on both axes.
+    fn layout_scale(&self) -> (f64, f64) {
+        let base_rect = self.base_rect();
+        match self.back_img.as_ref() {
+            Some(back_img) if back_img.width() > 0 && back_img.height() > 0 => (
+                base_rect.width() / back_img.width() as f64,
+                base_rect.height() / back_img.height() as f64,
+            ),
+            _ => (1., 1.),
+        }
+    }
+
+    /// The size of the zoomed screenshot, `None` when it fits the available width.
+    fn zoomed_size(&self) -> Option<Size> {
+        let back_img = self.back_img.as_ref()?;
+        let zoom = self.zoom?;
+        Some(Size::new(back_img.width() as f64 * zoom, back_img.height() as f64 * zoom))
+    }
+
+    /// The size of the ZStack: as wide as the available space, and high enough to work on the
+    /// zoomed screenshot.
+    fn viewport_size(&self) -> Size {
+        match self.zoomed_size() {
+            Some(zoomed) => Size::new(
+                self.fit_size.width,
+                self.fit_size.height.max(zoomed.height.min(ZOOM_VIEWPORT_HEIGHT)),
+            ),
+            None => self.fit_size,
+        }
+    }
+
+    /// Where the screenshot lies in the layout, it is computed from the zoom so that it is right
+    /// before the next layout too.
+    fn base_rect(&self) -> Rect {
+        match self.zoomed_size() {
+            Some(zoomed) => Rect::from_origin_size((-self.pan).to_point(), zoomed),
+            None => self.fit_size.to_rect(),
+        }
+    }
+
+    /// Keeps the zoomed screenshot inside the viewport, it is centered if it is smaller.
+    fn clamp_pan(&mut self) {
+        if let Some(zoomed) = self.zoomed_size() {
+            let viewport = self.viewport_size();
+            let clamp = |pan: f64, zoomed: f64, viewport: f64| {
+                if zoomed <= viewport {
+                    -(viewport - zoomed) / 2.
+                } else {
+                    pan.clamp(0., zoomed - viewport)
+                }
+            };
+            self.pan = Vec2::new(
+                clamp(self.pan.x, zoomed.width, viewport.width),
+                clamp(self.pan.y, zoomed.height, viewport.height),
             );
+        } else {
+            self.pan = Vec2::ZERO;
+        }
+    }
 
-            let mut over_img_rect: Rect = self.layers.get(0).unwrap().child.layout_rect();
-            over_img_rect.x0 = (over_img_rect.x0 * scale_factor_x).floor();
-            over_img_rect.y0 = (over_img_rect.y0 * scale_factor_y).floor();
-            over_img_rect.x1 = (over_img_rect.x1 * scale_factor_x).expand();
-            over_img_rect.y1 = (over_img_rect.y1 * scale_factor_y).expand();
-            let over_img = self.over_images.as_mut().unwrap()[self.showing_over_img.unwrap()]
-                .resize(
-                    over_img_rect.width() as u32,
-                    over_img_rect.height() as u32,
-                    FilterType::Nearest,
-                );
+    /// Changes the zoom keeping still the pixel under `anchor`, a point of the layout: the
+    /// showing over-image stays on the same pixels.
+    fn set_zoom(&mut self, zoom: Option<f64>, anchor: Point, id: WidgetId) {
+        let over_img_rect = match self.showing_over_img {
+            Some(_) if self.layers.len() > 1 => Some(self.to_back_img_rect(self.layers[0].child.layout_rect())),
+            _ => None,
+        };
+        let anchor_px = self.to_back_img_rect(Rect::from_points(anchor, anchor)).origin();
 
-            let mut out = back_img;
-            let mut i2: u32 = 0;
-            let mut j2: u32 = 0;
-            for j1 in over_img_rect.y0 as u32..(over_img_rect.y1) as u32 {
-                for i1 in over_img_rect.x0 as u32..(over_img_rect.x1) as u32 {
-                    if over_img.in_bounds(i2, j2) {
-                        let over_px = over_img.get_pixel(i2, j2);
-                        if out.in_bounds(i1, j1) && over_px.channels()[3] == u8::MAX {
-                            out.put_pixel(i1, j1, over_px);
-                        } else if out.in_bounds(i1, j1) && over_px.channels()[3] != 0 {
-                            let mut new_px = out.get_pixel(i1, j1);
-                            new_px.blend(&over_px);
-                            out.put_pixel(i1, j1, new_px);
-                        }
+        self.zoom = zoom.map(|zoom| zoom.clamp(0.01, MAX_ZOOM));
+        if let Some(zoom) = self.zoom {
+            self.pan = anchor_px.to_vec2() * zoom - anchor.to_vec2();
+        }
+        self.clamp_pan();
+
+        if let Some(rect) = over_img_rect {
+            let scale = self.layout_scale();
+            let size = Size::new(rect.width() * scale.0, rect.height() * scale.1);
+            self.place_over_img(self.to_layout_point(rect.origin()), size, id);
+        }
+    }
+
+    /// Adds a step marker where the user clicked, or removes the clicked one with the right
+    /// button: the following markers are numbered again.
+    fn edit_markers(&mut self, me: &MouseEvent) {
+        if let Some(point) = self.to_back_img_point(me.pos) {
+            if me.button == MouseButton::Right {
+                if let Some(index) = self.markers.iter().rposition(|marker| marker.contains(point)) {
+                    self.markers.remove(index);
+                    if index < self.markers_before {
+                        self.markers_before -= 1;
                     }
-                    i2 += 1;
                 }
-                i2 = 0;
-                j2 += 1;
+            } else if me.button == MouseButton::Left {
+                let color = self.color.0.unwrap_or(Color::RED);
+                self.markers.push(StepMarker::new(point, color));
             }
+        }
+    }
 
-            self.rm_over_img();
+    /// Returns the color of the pixel shown under the point of the layout, with the adjustments
+    /// and the annotations.
+    fn pick_color(&self, pos: Point) -> Option<Color> {
+        let point = self.to_back_img_point(pos)?;
+        let px = self.compose()?.get_pixel(point.x as u32, point.y as u32);
+        Some(Color::rgb8(px[0], px[1], px[2]))
+    }
 
-            out.save_with_format(new_img_path, img_format).unwrap();
-            self.back_img = Some(out.clone());
-            Some(out)
-        } else {
-            None
+    /// Converts a point of the ZStack layout into a pixel of the back image, moved inside the
+    /// screenshot if it is outside.
+    fn to_back_img_point_clamped(&self, pos: Point) -> Option<Point> {
+        let back_img = self.back_img.as_ref()?;
+        let point = self.to_back_img_rect(Rect::from_points(pos, pos)).origin();
+        Some(Point::new(
+            point.x.clamp(0., back_img.width() as f64),
+            point.y.clamp(0., back_img.height() as f64),
+        ))
+    }
+
+    /// Turns the highlighter stroke just drawn into the showing over-image.
+    fn finish_stroke(&mut self, id: WidgetId) {
+        let shape = match self.stroke_shape.take() {
+            Some(shape) => shape,
+            None => return,
+        };
+        let points = std::mem::take(&mut self.stroke);
+        let color = self.color.0.unwrap_or(Color::YELLOW);
+        if let Some((img, rect)) = stroke_to_image(&points, shape, color) {
+            let scale = self.layout_scale();
+            let size = Size::new(rect.width() * scale.0, rect.height() * scale.1);
+            let origin = self.to_layout_point(rect.origin());
+            self.over_img = Some(img);
+            self.showing_over_img = Some(STROKE_INDEX);
+            self.blend = BlendMode::Multiply;
+            self.place_over_img(origin, size, id);
         }
     }
-}
 
-fn calculate_text_width(font: Font, scale: Scale, text: &str) -> u32 {
-    let mut width = 0;
+    fn paint_stroke(&self, ctx: &mut PaintCtx) {
+        let (first, last) = match (self.stroke.first(), self.stroke.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return,
+        };
+        let scale = self.layout_scale();
+        let color = self.color.0.unwrap_or(Color::YELLOW).with_alpha(0.5);
+        match self.stroke_shape {
+            Some(StrokeShape::Freehand) => {
+                let mut path = BezPath::new();
+                path.move_to(self.to_layout_point(first));
+                for point in self.stroke.iter().skip(1) {
+                    path.line_to(self.to_layout_point(*point));
+                }
+                ctx.stroke(path, &color, STROKE_WIDTH * scale.0.min(scale.1));
+            }
+            Some(StrokeShape::Rectangle) => {
+                let rect = Rect::from_points(self.to_layout_point(first), self.to_layout_point(last));
+                ctx.fill(rect, &color);
+            }
+            None => {}
+        }
+    }
 
-    for c in text.chars() {
-        let glyph = font.glyph(c).scaled(scale);
-        width += glyph.h_metrics().advance_width.ceil() as u32;
+    /// Paints the outline and the shadow of the showing over-image, under it.
+    fn paint_style_preview(&mut self, ctx: &mut PaintCtx) {
+        let (over_img, layout_rect) = match self.over_img.as_ref() {
+            Some(over_img) if self.layers.len() > 1 => (over_img, self.layers[0].child.layout_rect()),
+            _ => return,
+        };
+        let rect = self.to_back_img_rect(layout_rect);
+        let size = Size::new(rect.width().round().max(1.), rect.height().round().max(1.));
+        let outdated = match self.style_preview.as_ref() {
+            Some((preview_size, preview_style, preview_transform, _, _)) => {
+                *preview_size != size || *preview_style != self.style || *preview_transform != self.transform
+            }
+            None => true,
+        };
+        if outdated {
+            let resized = over_img.resize_exact(size.width as u32, size.height as u32, FilterType::Nearest);
+            // the decoration follows the rotated over-image, the shadow always falls to the bottom right
+            let (transformed, padding) = self.transform.apply(&resized);
+            self.style_preview = self.style.decoration(&transformed).map(|(decoration, margin)| {
+                let image = ImageBuf::from_raw(
+                    Arc::<[u8]>::from(decoration.as_bytes()),
+                    ImageFormat::RgbaSeparate,
+                    decoration.width() as usize,
+                    decoration.height() as usize,
+                )
+                .to_image(ctx.render_ctx);
+                let growth = Vec2::new((padding.0 + margin) as f64, (padding.1 + margin) as f64);
+                (size, self.style, self.transform, growth, image)
+            });
+        }
+        if let Some((_, _, _, growth, image)) = self.style_preview.as_ref() {
+            let scale = self.layout_scale();
+            let rect = layout_rect.inflate(growth.x * scale.0, growth.y * scale.1);
+            ctx.draw_image(image, rect, InterpolationMode::Bilinear);
+        }
     }
 
-    width
+    fn paint_markers(&self, ctx: &mut PaintCtx) {
+        let scale = self.layout_scale();
+        let radius = MARKER_RADIUS * scale.0.min(scale.1);
+        for (i, marker) in self.markers.iter().enumerate() {
+            let center = self.to_layout_point(marker.center);
+            ctx.fill(Circle::new(center, radius), &marker.color);
+
+            let text = ctx
+                .text()
+                .new_text_layout((i + 1).to_string())
+                .font(FontFamily::SANS_SERIF, radius * 1.2)
+                .text_color(marker.text_color())
+                .build()
+                .unwrap();
+            let text_size = text.size();
+            ctx.draw_text(&text, center - text_size.to_vec2() / 2.);
+        }
+    }
 }
 
-fn text_to_image(text: &str, color: Option<Color>) -> DynamicImage {
-    let font_data: &[u8] = include_bytes!("../images/icons/DejaVuSans.ttf");
-    let font = Font::try_from_bytes(font_data).unwrap();
-
-    let scale = Scale::uniform(30.0);
-
-    let width = calculate_text_width(font.clone(), scale, text);
-    let height = 25;
-    let mut image = DynamicImage::new_rgba8(width, height);
-
-    let default_color = Color::rgba8(255, 255, 255, 255);
-    let colors = color.unwrap_or(default_color).as_rgba8();
-    draw_text_mut(
-        &mut image,
-        Rgba([colors.0, colors.1, colors.2, colors.3]),
-        0,
-        0,
-        scale,
-        &font,
-        text,
-    );
-
-    image
+fn redact_image() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, u8::MAX])))
 }
 
 impl<T: Data> Widget<T> for CustomZStack<T> {
@@ -293,10 +1258,14 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
         match event {
             Event::Command(cmd) => {
                 if cmd.is(commands::COPY) {
-                    if let Some(image) = self.back_img.clone() {
-                        let image_buffer = image.into_rgba8();
-                        let mut clipboard = clippers::Clipboard::get();
-                        clipboard.write_image(image_buffer.width(), image_buffer.height(), image_buffer.as_raw()).unwrap();
+                    match self.export_img() {
+                        Ok(Some(image)) => {
+                            let image_buffer = image.into_rgba8();
+                            let mut clipboard = clippers::Clipboard::get();
+                            clipboard.write_image(image_buffer.width(), image_buffer.height(), image_buffer.as_raw()).unwrap();
+                        }
+                        Ok(None) => {}
+                        Err(e) => ctx.submit_command(SHOW_ALERT.with(e)),
                     }
                 }
                 if cmd.is(SHOW_OVER_IMG) {
@@ -315,21 +1284,90 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
                             self.show_over_img(3, ctx.widget_id());
                         }
                         OverImages::Text => {
-                            if let Some(text) = text_field {
+                            if let Some((text, text_style)) = text_field {
                                 if text.len() > 0 {
                                     self.text_field = Some((*text).clone());
+                                    self.text_style = text_style.clone();
                                     self.show_over_img(4, ctx.widget_id());
                                 }
                             }
                         }
+                        OverImages::Redact => {
+                            self.show_over_img(REDACT_INDEX, ctx.widget_id());
+                        }
+                        OverImages::Stamp(index) => {
+                            if STAMPS_INDEX + index < self.over_images.as_ref().map_or(0, |over_images| over_images.len()) {
+                                self.show_over_img(STAMPS_INDEX + index, ctx.widget_id());
+                            }
+                        }
+                        OverImages::Marker => {
+                            if !self.placing_markers {
+                                self.markers_before = self.markers.len();
+                            }
+                            self.placing_markers = true;
+                        }
+                        OverImages::Stroke(shape) => {
+                            self.stroke_shape = Some(*shape);
+                            self.stroke.clear();
+                        }
+                        OverImages::Spotlight => {
+                            if !self.placing_spotlight {
+                                self.spotlight_regions_before = self.spotlight_regions.len();
+                            }
+                            self.placing_spotlight = true;
+                        }
+                        OverImages::Callout => {
+                            self.placing_callout = true;
+                        }
+                        OverImages::Measure(shape) => {
+                            if self.measure_shape.is_none() {
+                                self.measurements_before = self.measurements.len();
+                            }
+                            self.measure_shape = Some(*shape);
+                        }
                         OverImages::Remove => {
                             if self.showing_over_img.is_some() {
                                 self.show_over_img(0, ctx.widget_id());
                             }
+                            // the markers placed in this session are dropped, the previous
+                            // ones stay
+                            if self.placing_markers {
+                                self.markers.truncate(self.markers_before);
+                            }
+                            self.placing_markers = false;
+                            self.stroke_shape = None;
+                            self.stroke.clear();
+                            // the same for the spotlight
+                            if self.placing_spotlight {
+                                self.spotlight_regions.truncate(self.spotlight_regions_before);
+                                self.update_screenshot(ctx);
+                            }
+                            self.placing_spotlight = false;
+                            self.spotlight_drag = None;
+                            self.placing_callout = false;
+                            self.callout_drag = None;
+                            // the same for the measurements
+                            if self.measure_shape.is_some() {
+                                self.measurements.truncate(self.measurements_before);
+                            }
+                            self.measure_shape = None;
+                            self.measure_drag = None;
                         }
                     }
+                    if self.showing_over_img.is_some() {
+                        ctx.submit_command(BLEND_MODE_CHANGED.with(self.blend));
+                    }
                 } else if cmd.is(SAVE_OVER_IMG) {
                     let (path, file_name, file_format) = cmd.get_unchecked(SAVE_OVER_IMG);
+                    // only some formats keep the transparent background and the soft shadow
+                    let file_format = if self.beautify.needs_alpha() && !keeps_alpha(*file_format) {
+                        ctx.submit_command(SHOW_ALERT.with(
+                            "The background is transparent: the screenshot is saved as PNG.".to_string(),
+                        ));
+                        &imgFormat::Png
+                    } else {
+                        file_format
+                    };
                     let new_img_path = format!(
                         "{}{}.{}",
                         path,
@@ -340,14 +1378,83 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
                     // it verify if exists the dir before saving the image
                     verify_exists_dir(BASE_PATH_SCREENSHOT);
 
-                    let new_img = self.save_new_img(&new_img_path, *file_format);
-                    if new_img.is_some() {
-                        ctx.submit_command(
-                            UPDATE_SCREENSHOT
-                                .with(Arc::new(new_img.unwrap()))
-                                .to(Target::Widget(self.screenshot_id)),
-                        );
+                    if self.semi_transparent_over_text() {
+                        ctx.submit_command(SHOW_ALERT.with(
+                            "Warning: a semi-transparent shape covers some text, it may still be readable! Use the redact tool to hide it."
+                                .to_string(),
+                        ));
+                    }
+
+                    match self.save_new_img(&new_img_path, *file_format) {
+                        Ok(Some(new_img)) => {
+                            ctx.submit_command(
+                                UPDATE_SCREENSHOT
+                                    .with(Arc::new(new_img))
+                                    .to(Target::Widget(self.screenshot_id)),
+                            );
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            ctx.submit_command(SHOW_ALERT.with(format!("The screenshot has not been saved: {}", e)));
+                        }
                     }
+                } else if cmd.is(UPDATE_OVER_TEXT) {
+                    let (text, text_style) = cmd.get_unchecked(UPDATE_OVER_TEXT);
+                    if self.showing_over_img == Some(TEXT_INDEX) && text.len() > 0 {
+                        // the text is rendered again in the same place where it has been moved
+                        self.text_field = Some(text.clone());
+                        self.text_style = text_style.clone();
+                        self.refresh_over_img(ctx.widget_id());
+                    }
+                } else if cmd.is(SET_BLEND_MODE) {
+                    let blend = *cmd.get_unchecked(SET_BLEND_MODE);
+                    // the mode sent back after selecting an annotation doesn't change anything
+                    let changed = blend != self.blend;
+                    self.blend = blend;
+                    if changed && self.showing_over_img.is_some() && self.layers.len() > 1 {
+                        let over_img_rect = self.layers[0].child.layout_rect();
+                        self.place_over_img(over_img_rect.origin(), over_img_rect.size(), ctx.widget_id());
+                    }
+                } else if cmd.is(UPDATE_ROTATION) {
+                    self.transform.rotation = *cmd.get_unchecked(UPDATE_ROTATION);
+                } else if cmd.is(FLIP_OVER_IMG) {
+                    if self.showing_over_img.is_some() && self.layers.len() > 1 {
+                        // mirroring the rotated over-image is the same as mirroring it before
+                        // rotating it the other way
+                        match cmd.get_unchecked(FLIP_OVER_IMG) {
+                            Axis::Horizontal => self.transform.flip_horizontal = !self.transform.flip_horizontal,
+                            Axis::Vertical => self.transform.flip_vertical = !self.transform.flip_vertical,
+                        }
+                        self.transform.rotation = (-self.transform.rotation).rem_euclid(360.);
+                        let over_img_rect = self.layers[0].child.layout_rect();
+                        self.place_over_img(over_img_rect.origin(), over_img_rect.size(), ctx.widget_id());
+                    }
+                } else if cmd.is(SET_ANNOTATION_STYLE) {
+                    self.style = *cmd.get_unchecked(SET_ANNOTATION_STYLE);
+                } else if cmd.is(SET_ADJUSTMENTS) {
+                    self.adjustments = *cmd.get_unchecked(SET_ADJUSTMENTS);
+                    // the showing inset is a copy of the screenshot, it follows the adjustments
+                    if self.showing_over_img == Some(CALLOUT_INDEX) {
+                        self.refresh_over_img(ctx.widget_id());
+                    }
+                    self.update_screenshot(ctx);
+                } else if cmd.is(SET_SPOTLIGHT) {
+                    self.spotlight = *cmd.get_unchecked(SET_SPOTLIGHT);
+                    self.update_screenshot(ctx);
+                } else if cmd.is(SET_CALLOUT_CONNECTOR) {
+                    self.callout_connector = *cmd.get_unchecked(SET_CALLOUT_CONNECTOR);
+                    if let Some(callout) = self.callout.as_mut() {
+                        callout.connector = self.callout_connector;
+                    }
+                } else if cmd.is(SET_ZOOM) {
+                    let anchor = self.viewport_size().to_rect().center();
+                    self.set_zoom(*cmd.get_unchecked(SET_ZOOM), anchor, ctx.widget_id());
+                    ctx.submit_command(ZOOM_CHANGED.with(self.layout_scale().0));
+                    ctx.request_layout();
+                } else if cmd.is(PAN_KEY) {
+                    self.pan_key = *cmd.get_unchecked(PAN_KEY);
+                } else if cmd.is(START_EYEDROPPER) {
+                    self.picking_color = true;
                 } else if cmd.is(UPDATE_ORIGIN) {
                     let new_origin = cmd.get_unchecked(UPDATE_ORIGIN);
                     self.back_img_origin = Some(*new_origin);
@@ -361,36 +1468,11 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
                     }
 
                     //update images color
-                    if self.over_images.is_some() {
-                        self.over_images
-                            .as_mut()
-                            .unwrap()
-                            .iter_mut()
-                            .for_each(|img| {
-                                let (color, alpha) = self.color;
-                                for j in 0..img.height() {
-                                    for i in 0..img.width() {
-                                        let mut cur_px = img.get_pixel(i, j);
-                                        let ch = cur_px.channels_mut();
-                                        if ch[3] > 0 {
-                                            if color.is_some() {
-                                                let color = color.unwrap().as_rgba8();
-                                                ch[0] = color.0;
-                                                ch[1] = color.1;
-                                                ch[2] = color.2;
-                                            }
-                                            ch[3] = ((alpha / 100.) * u8::MAX as f64) as u8;
-                                            img.put_pixel(i, j, cur_px);
-                                        }
-                                    }
-                                }
-                            });
-                    }
-                    if self.showing_over_img.is_some() {
-                        self.rm_over_img();
-                    }
+                    self.color_over_images();
+                    // the selected over-image is recolored where it is
+                    self.refresh_over_img(ctx.widget_id());
                 } else if cmd.is(CREATE_ZSTACK) {
-                    let paths = cmd.get_unchecked(CREATE_ZSTACK);
+                    let (paths, stamps) = cmd.get_unchecked(CREATE_ZSTACK);
                     let mut over_images = Vec::<DynamicImage>::new();
                     paths.iter().for_each(|path| {
                         let over_img = Reader::open(path)
@@ -399,14 +1481,295 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
                             .expect("Can't decode the screenshot");
                         over_images.push(over_img);
                     });
+                    // the text over-image is generated on demand, here its slot is only reserved
+                    over_images.insert(TEXT_INDEX, DynamicImage::new_rgba8(1, 1));
+                    over_images.insert(REDACT_INDEX, redact_image());
+
+                    // the stamps are decoded once by the stamp picker, the unreadable ones already skipped
+                    self.stamps = stamps.clone();
+                    over_images.extend(self.stamps.iter().cloned());
                     self.over_images = Some(over_images);
                 } else if cmd.is(UPDATE_BACK_IMG) {
                     let back_img = cmd.get_unchecked(UPDATE_BACK_IMG);
+                    // a new screenshot: the annotations of the previous one are dropped
                     self.back_img = Some((**back_img).clone());
+                    self.capture = Some((**back_img).clone());
+                    self.crop = None;
+                    self.annotations.clear();
+                    self.markers.clear();
+                    self.markers_before = 0;
+                    self.measurements.clear();
+                    self.measurements_before = 0;
+                    self.spotlight_regions.clear();
+                    self.spotlight_regions_before = 0;
+                    self.adjustments = Adjustments::default();
+                    self.capture_info = CaptureInfo::now(None);
+                } else if cmd.is(CAPTURE_INFO) {
+                    self.capture_info = *cmd.get_unchecked(CAPTURE_INFO);
+                } else if cmd.is(SET_WATERMARK) {
+                    self.watermark = cmd.get_unchecked(SET_WATERMARK).clone();
+                } else if cmd.is(SET_EXPORT_SCALE) {
+                    self.export_scale = *cmd.get_unchecked(SET_EXPORT_SCALE);
+                } else if cmd.is(SET_BEAUTIFY) {
+                    self.beautify = *cmd.get_unchecked(SET_BEAUTIFY);
+                } else if cmd.is(SET_ENCODE) {
+                    self.encode = *cmd.get_unchecked(SET_ENCODE);
+                } else if cmd.is(ORIENT_SCREENSHOT) {
+                    self.orient(*cmd.get_unchecked(ORIENT_SCREENSHOT));
+                    self.update_screenshot(ctx);
+                    ctx.request_layout();
+                } else if cmd.is(START_CROP) {
+                    // the selection starts from the current crop, shown on the whole capture
+                    self.commit_over_img();
+                    self.crop_before = self.crop;
+                    if let Some(capture) = self.capture.as_ref() {
+                        let whole = Rect::new(0., 0., capture.width() as f64, capture.height() as f64);
+                        ctx.submit_command(SET_CROP_SELECTION.with(self.crop.unwrap_or(whole)));
+                    }
+                    self.set_crop(None);
+                    self.update_screenshot(ctx);
+                } else if cmd.is(CANCEL_CROP) {
+                    self.set_crop(self.crop_before.take());
+                    self.update_screenshot(ctx);
+                } else if cmd.is(RESET_CROP) {
+                    self.crop_before = None;
+                    self.set_crop(None);
+                    self.update_screenshot(ctx);
+                } else if cmd.is(CROP_BACK_IMG) {
+                    let (rect, size) = cmd.get_unchecked(CROP_BACK_IMG);
+                    let origin = self.crop.map(|crop| crop.origin()).unwrap_or(Point::ZERO);
+                    // a rect chosen on a back image that has been cropped in the meantime is stale
+                    let current = self.back_img.as_ref().map(|back_img| {
+                        Size::new(back_img.width() as f64, back_img.height() as f64)
+                    });
+                    if current == Some(*size) {
+                        self.crop_before = None;
+                        self.set_crop(Some(*rect + origin.to_vec2()));
+                        self.update_screenshot(ctx);
+                    }
+                } else if cmd.is(WRITE_PROJECT) {
+                    let (path, name, extension) = cmd.get_unchecked(WRITE_PROJECT);
+                    if let Some(capture) = self.capture.as_ref() {
+                        // the over-image still being edited is saved as well
+                        let mut annotations = self.annotations.clone();
+                        annotations.extend(self.showing_annotation());
+                        let project = Project::new(
+                            capture,
+                            self.crop,
+                            &annotations,
+                            &self.markers,
+                            self.color,
+                            name.clone(),
+                            extension.clone(),
+                        )
+                        .with_spotlight(&self.spotlight_regions, self.spotlight)
+                        .with_measurements(&self.measurements)
+                        .with_adjustments(self.adjustments)
+                        .with_capture_info(self.capture_info);
+                        let message = match write_to_file(path, &project) {
+                            Ok(_) => "The project has been saved!".to_string(),
+                            Err(e) => format!("Error saving the project: {}", e),
+                        };
+                        ctx.submit_command(SHOW_ALERT.with(message));
+                    } else {
+                        ctx.submit_command(SHOW_ALERT.with("Take a screenshot before saving a project!".to_string()));
+                    }
+                } else if cmd.is(LOAD_PROJECT) {
+                    let project = cmd.get_unchecked(LOAD_PROJECT);
+                    if let Some(capture) = project.capture() {
+                        self.rm_over_img();
+                        self.placing_markers = false;
+                        self.capture = Some(capture);
+                        self.crop = None;
+                        self.annotations.clear();
+                        self.markers.clear();
+                        self.measurements.clear();
+                        self.set_crop(project.crop());
+                        // the annotations are saved in pixels of the cropped image
+                        self.annotations = project.annotations();
+                        self.markers = project.markers();
+                        self.measurements = project.measurements();
+                        self.measurements_before = self.measurements.len();
+                        self.capture_info = project.capture_info();
+                        (self.spotlight_regions, self.spotlight) = project.spotlight();
+                        self.spotlight_regions_before = self.spotlight_regions.len();
+                        self.adjustments = project.adjustments();
+
+                        self.color = project.color();
+                        self.color_over_images();
+
+                        self.update_screenshot(ctx);
+                    } else {
+                        ctx.submit_command(SHOW_ALERT.with("The project is damaged, the screenshot can't be read!".to_string()));
+                    }
+                }
+                ctx.children_changed();
+                ctx.request_paint();
+            }
+            Event::Wheel(me) if me.mods.ctrl() && self.back_img.is_some() => {
+                let scale = self.layout_scale().0;
+                let fit_scale = self.fit_size.width / self.back_img.as_ref().unwrap().width().max(1) as f64;
+                let zoom = if me.wheel_delta.y < 0. {
+                    scale * ZOOM_STEP
+                } else {
+                    scale / ZOOM_STEP
+                };
+                // zooming out stops when the screenshot fits again
+                let zoom = if zoom <= fit_scale { None } else { Some(zoom) };
+                self.set_zoom(zoom, me.pos, ctx.widget_id());
+                ctx.submit_command(ZOOM_CHANGED.with(self.layout_scale().0));
+                ctx.children_changed();
+                ctx.request_layout();
+                ctx.set_handled();
+            }
+            Event::MouseDown(me)
+                if self.zoom.is_some()
+                    && (me.button == MouseButton::Middle || (self.pan_key && me.button == MouseButton::Left)) =>
+            {
+                self.panning = Some(me.pos);
+                ctx.set_active(true);
+                ctx.override_cursor(&Cursor::OpenHand);
+                ctx.set_handled();
+            }
+            Event::MouseMove(me) if self.panning.is_some() => {
+                self.pan -= me.pos - self.panning.unwrap();
+                self.panning = Some(me.pos);
+                self.clamp_pan();
+                ctx.request_layout();
+                ctx.set_handled();
+            }
+            Event::MouseUp(_) if self.panning.is_some() => {
+                self.panning = None;
+                ctx.set_active(false);
+                ctx.clear_cursor();
+                ctx.set_handled();
+            }
+            Event::MouseDown(me) if self.picking_color => {
+                if let Some(color) = self.pick_color(me.pos) {
+                    ctx.submit_command(COLOR_PICKED.with(color));
+                }
+                self.picking_color = false;
+                ctx.clear_cursor();
+                ctx.set_handled();
+            }
+            Event::MouseMove(_) if self.picking_color => {
+                ctx.override_cursor(&Cursor::Crosshair);
+            }
+            Event::MouseDown(me) if self.placing_markers => {
+                self.edit_markers(me);
+                ctx.set_handled();
+                ctx.request_paint();
+            }
+            Event::MouseDown(me) if self.placing_spotlight => {
+                self.edit_spotlight(me);
+                if self.spotlight_drag.is_some() {
+                    ctx.set_active(true);
+                } else {
+                    self.update_screenshot(ctx);
+                }
+                ctx.set_handled();
+            }
+            Event::MouseMove(me) if self.placing_spotlight && ctx.is_active() => {
+                if let (Some(point), Some((start, _))) = (self.to_back_img_point_clamped(me.pos), self.spotlight_drag) {
+                    self.spotlight_drag = Some((start, point));
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(_) if self.placing_spotlight && ctx.is_active() => {
+                ctx.set_active(false);
+                self.finish_spotlight_region();
+                self.update_screenshot(ctx);
+                ctx.request_paint();
+            }
+            Event::MouseDown(me) if self.measure_shape.is_some() => {
+                self.edit_measurements(me);
+                if self.measure_drag.is_some() {
+                    ctx.set_active(true);
+                }
+                ctx.set_handled();
+                ctx.request_paint();
+            }
+            Event::MouseMove(me) if self.measure_shape.is_some() => {
+                ctx.override_cursor(&Cursor::Crosshair);
+                if let (Some(point), Some((start, _)), true) =
+                    (self.to_back_img_point_clamped(me.pos), self.measure_drag, ctx.is_active())
+                {
+                    self.measure_drag = Some((start, point));
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(_) if self.measure_shape.is_some() && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.clear_cursor();
+                self.finish_measurement();
+                ctx.request_paint();
+            }
+            Event::MouseDown(me) if self.placing_callout && me.button == MouseButton::Left => {
+                if let Some(point) = self.to_back_img_point(me.pos) {
+                    self.callout_drag = Some((point, point));
+                    ctx.set_active(true);
+                }
+                ctx.set_handled();
+            }
+            Event::MouseMove(me) if self.placing_callout => {
+                ctx.override_cursor(&Cursor::Crosshair);
+                if let (Some(point), Some((start, _)), true) =
+                    (self.to_back_img_point_clamped(me.pos), self.callout_drag, ctx.is_active())
+                {
+                    self.callout_drag = Some((start, point));
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(_) if self.placing_callout && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.clear_cursor();
+                self.finish_callout(ctx.widget_id());
+                if self.showing_over_img.is_some() {
+                    ctx.submit_command(BLEND_MODE_CHANGED.with(self.blend));
+                }
+                ctx.children_changed();
+                ctx.request_paint();
+            }
+            Event::MouseDown(me) if self.stroke_shape.is_some() && me.button == MouseButton::Left => {
+                if let Some(point) = self.to_back_img_point(me.pos) {
+                    self.stroke = vec![point];
+                    ctx.set_active(true);
+                }
+                ctx.set_handled();
+            }
+            Event::MouseMove(me) if self.stroke_shape.is_some() => {
+                ctx.override_cursor(&Cursor::Crosshair);
+                if ctx.is_active() {
+                    if let Some(point) = self.to_back_img_point_clamped(me.pos) {
+                        self.stroke.push(point);
+                    }
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(_) if self.stroke_shape.is_some() && ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.clear_cursor();
+                self.finish_stroke(ctx.widget_id());
+                if self.showing_over_img.is_some() {
+                    ctx.submit_command(BLEND_MODE_CHANGED.with(self.blend));
                 }
                 ctx.children_changed();
                 ctx.request_paint();
             }
+            Event::MouseDown(me) if self.showing_over_img.is_none() && me.button == MouseButton::Left => {
+                if let Some(annotation) = self.select_annotation(me.pos, ctx.widget_id()) {
+                    ctx.submit_command(ANNOTATION_SELECTED.with(annotation.text));
+                    ctx.submit_command(BLEND_MODE_CHANGED.with(self.blend));
+                    ctx.submit_command(ANNOTATION_STYLE_CHANGED.with(self.style));
+                    self.update_screenshot(ctx);
+                    ctx.children_changed();
+                    ctx.set_handled();
+                } else {
+                    for layer in self.layers.iter_mut() {
+                        layer.child.event(ctx, event, data, env);
+                    }
+                }
+            }
             _ => {
                 let mut previous_hot = false;
                 for layer in self.layers.iter_mut() {
@@ -451,8 +1814,19 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
     fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
         //Layout base layer
 
+        // the screenshot is laid out to fit the available width first, then as large as the zoom
+        // says
+        let zoomed_size = self.zoomed_size();
         let base_layer = self.layers.last_mut().unwrap();
-        let base_size = base_layer.child.layout(ctx, bc, data, env);
+        let fit_size = base_layer.child.layout(ctx, bc, data, env);
+        let base_size = match zoomed_size {
+            Some(zoomed_size) => base_layer.child.layout(ctx, &BoxConstraints::tight(zoomed_size), data, env),
+            None => fit_size,
+        };
+        self.fit_size = fit_size;
+        self.clamp_pan();
+        let size = self.viewport_size();
+        let base_origin = self.base_rect().origin();
 
         //Layout other layers
         let other_layers = self.layers.len() - 1;
@@ -470,7 +1844,8 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
         let len = self.layers.len();
         for (i, layer) in self.layers.iter_mut().enumerate() {
             let remaining = base_size - layer.child.layout_rect().size();
-            let mut origin = layer.resolve_point(remaining);
+            // every layer moves together with the scrolled screenshot
+            let mut origin = layer.resolve_point(remaining) + base_origin.to_vec2();
             if self.back_img_origin.is_some() && i == 0 && len == 2 {
                 let dif_point = self.back_img_origin.unwrap();
                 origin.x += dif_point.x;
@@ -482,17 +1857,33 @@ impl<T: Data> Widget<T> for CustomZStack<T> {
             paint_rect = paint_rect.union(layer.child.paint_rect());
         }
 
-        ctx.set_paint_insets(paint_rect - base_size.to_rect());
+        // the zoomed screenshot is clipped to the viewport
+        if zoomed_size.is_some() {
+            paint_rect = size.to_rect();
+        }
+        ctx.set_paint_insets(paint_rect - size.to_rect());
         ctx.set_baseline_offset(self.layers.last().unwrap().child.baseline_offset());
 
-        base_size
+        size
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        if self.zoom.is_some() {
+            let viewport = ctx.size().to_rect();
+            ctx.clip(viewport);
+        }
         //Painters algorithm (Painting back to front)
-        for layer in self.layers.iter_mut().rev() {
+        self.layers.last_mut().unwrap().child.paint(ctx, data, env);
+        // the outline and the shadow lie between the screenshot and the over-image
+        self.paint_style_preview(ctx);
+        for layer in self.layers.iter_mut().rev().skip(1) {
             layer.child.paint(ctx, data, env);
         }
+        self.paint_stroke(ctx);
+        self.paint_spotlight_drag(ctx);
+        self.paint_callout(ctx);
+        self.paint_measurements(ctx);
+        self.paint_markers(ctx);
     }
 }
 
@@ -0,0 +1,308 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use druid::{Data, Lens, Selector};
+#[cfg(feature = "avif")]
+use image::codecs::avif::AvifEncoder;
+use image::codecs::webp::{WebPEncoder, WebPQuality};
+use image::imageops::{overlay, FilterType};
+use image::{ColorType, DynamicImage, GrayImage, ImageEncoder, ImageFormat, Luma, Pixel, Rgba, RgbaImage};
+use imageproc::filter::gaussian_blur_f32;
+use serde::{Deserialize, Serialize};
+
+pub const SET_EXPORT_SCALE: Selector<ExportScale> =
+    Selector::new("Tell the ZStack the size of the exported image, params: export scale");
+pub const SET_BEAUTIFY: Selector<Beautify> =
+    Selector::new("Tell the ZStack how to frame the exported image, params: beautify settings");
+pub const SET_ENCODE: Selector<EncodeSettings> =
+    Selector::new("Tell the ZStack how to encode the WebP and AVIF images, params: encode settings");
+
+/// the formats the screenshots can be saved in: the name shown and the extension
+#[cfg(feature = "avif")]
+pub const SAVE_FORMATS: &[(&str, &str)] = &[
+    ("PNG", "png"),
+    ("JPG", "jpg"),
+    ("GIF", "gif"),
+    ("WEBP", "webp"),
+    ("AVIF", "avif"),
+];
+/// the formats the screenshots can be saved in: the name shown and the extension
+#[cfg(not(feature = "avif"))]
+pub const SAVE_FORMATS: &[(&str, &str)] = &[("PNG", "png"), ("JPG", "jpg"), ("GIF", "gif"), ("WEBP", "webp")];
+
+/// the smallest and the largest percentage of the size of the capture
+pub const EXPORT_PERCENT_RANGE: (f64, f64) = (1., 400.);
+/// the longest side, in pixels, an exported image can be enlarged to
+pub const EXPORT_MAX_DIMENSION: u32 = 10000;
+
+/// opacity of the darkest part of the shadow under the framed screenshot
+const BEAUTIFY_SHADOW_ALPHA: f64 = 0.5;
+
+/// How the size of the exported image is chosen, the aspect ratio is always kept.
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ScaleMode {
+    /// the size of the capture
+    Original,
+    /// a percentage of the size of the capture
+    Percent,
+    /// a width in pixels
+    Width,
+    /// a height in pixels
+    Height,
+}
+
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ResampleFilter {
+    Lanczos3,
+    Triangle,
+    Nearest,
+}
+
+impl ResampleFilter {
+    fn filter_type(&self) -> FilterType {
+        match self {
+            ResampleFilter::Lanczos3 => FilterType::Lanczos3,
+            ResampleFilter::Triangle => FilterType::Triangle,
+            ResampleFilter::Nearest => FilterType::Nearest,
+        }
+    }
+}
+
+/// The size of the exported image.
+#[derive(Clone, Copy, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct ExportScale {
+    pub(crate) mode: ScaleMode,
+    /// the percentage, the width or the height, depending on the mode
+    pub(crate) value: f64,
+    pub(crate) filter: ResampleFilter,
+}
+
+impl Default for ExportScale {
+    fn default() -> Self {
+        Self {
+            mode: ScaleMode::Original,
+            value: 100.,
+            filter: ResampleFilter::Lanczos3,
+        }
+    }
+}
+
+impl ExportScale {
+    /// The value brought in the range allowed by the mode.
+    pub fn clamp_value(&self, value: f64) -> f64 {
+        let (min, max) = match self.mode {
+            ScaleMode::Original => return value,
+            ScaleMode::Percent => EXPORT_PERCENT_RANGE,
+            ScaleMode::Width | ScaleMode::Height => (1., EXPORT_MAX_DIMENSION as f64),
+        };
+        value.max(min).min(max)
+    }
+
+    /// The size of the exported image, for an image of the given size: it is never enlarged past
+    /// the maximum dimension, even with settings that skipped the clamp of the text box.
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = (width as f64, height as f64);
+        let value = self.clamp_value(self.value);
+        let factor = match self.mode {
+            ScaleMode::Original => 1.,
+            ScaleMode::Percent => value / 100.,
+            ScaleMode::Width => value / width.max(1.),
+            ScaleMode::Height => value / height.max(1.),
+        };
+        let max_factor = (EXPORT_MAX_DIMENSION as f64 / width.max(height).max(1.)).max(1.);
+        let factor = factor.min(max_factor);
+        (
+            (width * factor).round().max(1.) as u32,
+            (height * factor).round().max(1.) as u32,
+        )
+    }
+
+    /// Resizes the image, it is returned untouched if its size doesn't change.
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        let (width, height) = self.size(img.width(), img.height());
+        if (width, height) == (img.width(), img.height()) {
+            return img;
+        }
+        img.resize_exact(width, height, self.filter.filter_type())
+    }
+}
+
+/// What is drawn around the framed screenshot.
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BeautifyBackground {
+    Transparent,
+    White,
+    Dark,
+    /// a gradient from orange to purple
+    Sunset,
+    /// a gradient from cyan to blue
+    Ocean,
+}
+
+impl BeautifyBackground {
+    /// The color at the top-left corner and the one at the bottom-right corner.
+    fn colors(&self) -> (Rgba<u8>, Rgba<u8>) {
+        match self {
+            BeautifyBackground::Transparent => (Rgba([0, 0, 0, 0]), Rgba([0, 0, 0, 0])),
+            BeautifyBackground::White => (Rgba([255, 255, 255, 255]), Rgba([255, 255, 255, 255])),
+            BeautifyBackground::Dark => (Rgba([34, 34, 40, 255]), Rgba([34, 34, 40, 255])),
+            BeautifyBackground::Sunset => (Rgba([255, 154, 68, 255]), Rgba([140, 60, 200, 255])),
+            BeautifyBackground::Ocean => (Rgba([70, 220, 230, 255]), Rgba([40, 80, 200, 255])),
+        }
+    }
+}
+
+/// The screenshot placed on a larger canvas, with rounded corners and a soft shadow, the sizes are
+/// in pixels of the exported image.
+#[derive(Clone, Copy, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct Beautify {
+    pub(crate) enabled: bool,
+    pub(crate) padding: f64,
+    pub(crate) background: BeautifyBackground,
+    pub(crate) corner_radius: f64,
+    pub(crate) shadow: bool,
+}
+
+impl Default for Beautify {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            padding: 64.,
+            background: BeautifyBackground::Sunset,
+            corner_radius: 12.,
+            shadow: true,
+        }
+    }
+}
+
+impl Beautify {
+    /// Checks if the framed image has transparent pixels, that only some formats can keep.
+    pub fn needs_alpha(&self) -> bool {
+        self.enabled && self.background == BeautifyBackground::Transparent
+    }
+
+    /// Puts the image on the canvas, it is returned untouched if the style is disabled.
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        if !self.enabled {
+            return img;
+        }
+        let (width, height) = (img.width(), img.height());
+        let padding = self.padding.round().max(0.) as u32;
+        let (canvas_width, canvas_height) = (width + 2 * padding, height + 2 * padding);
+
+        let (start, end) = self.background.colors();
+        let mut canvas = RgbaImage::from_fn(canvas_width, canvas_height, |x, y| {
+            // the gradient goes along the diagonal
+            let t = (x + y) as f64 / (canvas_width + canvas_height).saturating_sub(2).max(1) as f64;
+            Rgba([0, 1, 2, 3].map(|c| (start[c] as f64 + (end[c] as f64 - start[c] as f64) * t).round() as u8))
+        });
+
+        let mask = rounded_mask(width, height, self.corner_radius.max(0.));
+        if self.shadow && padding > 0 {
+            // the shadow is as soft as the padding allows, and falls a little below the image
+            let blur = (padding as f32 / 4.).min(20.);
+            let mut shape = GrayImage::new(canvas_width, canvas_height);
+            let offset = padding + (blur / 2.) as u32;
+            for (x, y, px) in mask.enumerate_pixels() {
+                if y + offset < canvas_height {
+                    shape.put_pixel(x + padding, y + offset, *px);
+                }
+            }
+            let shadow = gaussian_blur_f32(&shape, blur);
+            for (x, y, px) in shadow.enumerate_pixels() {
+                if px[0] > 0 {
+                    let alpha = (px[0] as f64 * BEAUTIFY_SHADOW_ALPHA).round() as u8;
+                    canvas.get_pixel_mut(x, y).blend(&Rgba([0, 0, 0, alpha]));
+                }
+            }
+        }
+
+        let mut screenshot = img.into_rgba8();
+        for (x, y, px) in screenshot.enumerate_pixels_mut() {
+            px[3] = (px[3] as u32 * mask.get_pixel(x, y)[0] as u32 / u8::MAX as u32) as u8;
+        }
+        overlay(&mut canvas, &screenshot, padding as i64, padding as i64);
+        DynamicImage::ImageRgba8(canvas)
+    }
+}
+
+/// The coverage of a rect with rounded corners, the borders of the corners are smoothed.
+fn rounded_mask(width: u32, height: u32, radius: f64) -> GrayImage {
+    let radius = radius.min(width as f64 / 2.).min(height as f64 / 2.);
+    GrayImage::from_fn(width, height, |x, y| {
+        let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+        // the center of the nearest corner circle, the pixels between the circles are covered
+        let cx = px.clamp(radius, width as f64 - radius);
+        let cy = py.clamp(radius, height as f64 - radius);
+        let distance = (px - cx).hypot(py - cy);
+        let coverage = if distance == 0. {
+            1.
+        } else {
+            (radius - distance + 0.5).clamp(0., 1.)
+        };
+        Luma([(coverage * u8::MAX as f64).round() as u8])
+    })
+}
+
+/// the lowest and the highest quality of the lossy WebP and of the AVIF images
+pub const ENCODE_QUALITY_RANGE: (f64, f64) = (5., 100.);
+
+/// How the formats with a quality are encoded, the qualities are in `ENCODE_QUALITY_RANGE`.
+#[derive(Clone, Copy, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct EncodeSettings {
+    /// the lossless WebP keeps the text sharp, the lossy one makes smaller files
+    pub(crate) webp_lossless: bool,
+    pub(crate) webp_quality: f64,
+    pub(crate) avif_quality: f64,
+    /// from 1 (slowest, smallest file) to 10 (fastest)
+    pub(crate) avif_speed: f64,
+}
+
+impl Default for EncodeSettings {
+    fn default() -> Self {
+        Self {
+            webp_lossless: true,
+            webp_quality: 80.,
+            avif_quality: 70.,
+            avif_speed: 6.,
+        }
+    }
+}
+
+/// Checks if the format keeps the transparent pixels, the soft shadows included.
+pub fn keeps_alpha(format: ImageFormat) -> bool {
+    matches!(format, ImageFormat::Png | ImageFormat::WebP | ImageFormat::Avif)
+}
+
+/// Checks if the image can be saved in the format.
+pub fn is_save_format(format: ImageFormat) -> bool {
+    SAVE_FORMATS
+        .iter()
+        .any(|(_, extension)| format.extensions_str().contains(extension))
+}
+
+/// Saves the image in the format, the WebP and AVIF images are encoded with the settings.
+pub fn save_image(img: &DynamicImage, path: impl AsRef<Path>, format: ImageFormat, encode: &EncodeSettings) -> Result<(), String> {
+    let result = match format {
+        ImageFormat::WebP => {
+            let rgba = img.to_rgba8();
+            let file = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+            let quality = if encode.webp_lossless {
+                WebPQuality::lossless()
+            } else {
+                WebPQuality::lossy(encode.webp_quality.round().clamp(ENCODE_QUALITY_RANGE.0, ENCODE_QUALITY_RANGE.1) as u8)
+            };
+            WebPEncoder::new_with_quality(file, quality).write_image(rgba.as_raw(), rgba.width(), rgba.height(), ColorType::Rgba8)
+        }
+        #[cfg(feature = "avif")]
+        ImageFormat::Avif => {
+            let rgba = img.to_rgba8();
+            let file = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+            let speed = encode.avif_speed.round().clamp(1., 10.) as u8;
+            let quality = encode.avif_quality.round().clamp(ENCODE_QUALITY_RANGE.0, ENCODE_QUALITY_RANGE.1) as u8;
+            AvifEncoder::new_with_speed_quality(file, speed, quality).write_image(rgba.as_raw(), rgba.width(), rgba.height(), ColorType::Rgba8)
+        }
+        _ => img.save_with_format(path, format),
+    };
+    result.map_err(|e| e.to_string())
+}
@@ -0,0 +1,163 @@
+use druid::{Color, Point, Rect, Vec2};
+use image::{DynamicImage, Rgba};
+use imageproc::drawing::{draw_filled_rect_mut, draw_line_segment_mut, draw_text_mut, text_size};
+use rusttype::Scale;
+use serde::{Deserialize, Serialize};
+use crate::custom_widget::text_style::{contrast_color, load_font};
+
+/// size of the font of the labels, in pixels of the screenshot
+pub const MEASURE_LABEL_SIZE: f64 = 16.;
+/// padding around the text of a label
+pub const MEASURE_LABEL_PADDING: f64 = 3.;
+/// half the length of the ticks at the ends of a dimension line
+const MEASURE_TICK: f64 = 6.;
+/// how far from a measurement a right click still removes it
+const MEASURE_HIT_DISTANCE: f64 = 6.;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum MeasureShape {
+    /// a dimension line labeled with its length
+    Line,
+    /// a box labeled with its width and height
+    Box,
+}
+
+/// A measurement drawn on the screenshot, its points are pixels of the screenshot so that the
+/// labels never depend on how large the screenshot is shown.
+#[derive(Clone)]
+pub struct Measurement {
+    pub(crate) start: Point,
+    pub(crate) end: Point,
+    pub(crate) shape: MeasureShape,
+    pub(crate) color: Color,
+}
+
+impl Measurement {
+    pub fn new(start: Point, end: Point, shape: MeasureShape, color: Color) -> Self {
+        Self {
+            start,
+            end,
+            shape,
+            color,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self.shape {
+            MeasureShape::Line => format!("{} px", self.start.distance(self.end).round()),
+            MeasureShape::Box => {
+                let rect = Rect::from_points(self.start, self.end);
+                format!("{}×{}", rect.width().round(), rect.height().round())
+            }
+        }
+    }
+
+    /// The center of the top side of the label: under the middle of the line, or under the box.
+    pub fn label_anchor(&self) -> Point {
+        match self.shape {
+            MeasureShape::Line => self.start.midpoint(self.end) + Vec2::new(0., MEASURE_TICK),
+            MeasureShape::Box => {
+                let rect = Rect::from_points(self.start, self.end);
+                Point::new(rect.center().x, rect.y1 + MEASURE_LABEL_PADDING)
+            }
+        }
+    }
+
+    /// The segments to draw: the line with a tick at both ends, or the sides of the box.
+    pub fn segments(&self) -> Vec<(Point, Point)> {
+        match self.shape {
+            MeasureShape::Line => {
+                let direction = self.end - self.start;
+                let length = direction.hypot();
+                if length == 0. {
+                    return Vec::new();
+                }
+                let normal = Vec2::new(-direction.y, direction.x) / length * MEASURE_TICK;
+                vec![
+                    (self.start, self.end),
+                    (self.start - normal, self.start + normal),
+                    (self.end - normal, self.end + normal),
+                ]
+            }
+            MeasureShape::Box => {
+                let rect = Rect::from_points(self.start, self.end);
+                let corners = [
+                    Point::new(rect.x0, rect.y0),
+                    Point::new(rect.x1, rect.y0),
+                    Point::new(rect.x1, rect.y1),
+                    Point::new(rect.x0, rect.y1),
+                ];
+                (0..4).map(|i| (corners[i], corners[(i + 1) % 4])).collect()
+            }
+        }
+    }
+
+    /// The color of the text of the label.
+    pub fn text_color(&self) -> Color {
+        let text_color = contrast_color(rgba(&self.color));
+        Color::rgba8(text_color[0], text_color[1], text_color[2], text_color[3])
+    }
+
+    /// Checks if a pixel of the screenshot lies on the measurement.
+    pub fn contains(&self, point: Point) -> bool {
+        self.segments().iter().any(|(start, end)| {
+            let segment = *end - *start;
+            let length = segment.hypot2();
+            let t = if length == 0. {
+                0.
+            } else {
+                ((point - *start).dot(segment) / length).clamp(0., 1.)
+            };
+            point.distance(*start + segment * t) <= MEASURE_HIT_DISTANCE
+        })
+    }
+}
+
+fn rgba(color: &Color) -> Rgba<u8> {
+    let (r, g, b, _) = color.as_rgba8();
+    Rgba([r, g, b, u8::MAX])
+}
+
+/// Draws the measurements on the image, with their labels.
+pub fn draw_measurements(img: &mut DynamicImage, measurements: &[Measurement]) {
+    let font = load_font();
+    let scale = Scale::uniform(MEASURE_LABEL_SIZE as f32);
+    let v_metrics = font.v_metrics(scale);
+    let text_height = (v_metrics.ascent - v_metrics.descent).ceil() as i32;
+    let padding = MEASURE_LABEL_PADDING as i32;
+
+    for measurement in measurements.iter() {
+        let color = rgba(&measurement.color);
+        for (start, end) in measurement.segments() {
+            // the lines are two pixels wide, to be seen on any background
+            for offset in 0..2 {
+                let offset = offset as f32;
+                draw_line_segment_mut(
+                    img,
+                    (start.x as f32 + offset, start.y as f32),
+                    (end.x as f32 + offset, end.y as f32),
+                    color,
+                );
+                draw_line_segment_mut(
+                    img,
+                    (start.x as f32, start.y as f32 + offset),
+                    (end.x as f32, end.y as f32 + offset),
+                    color,
+                );
+            }
+        }
+
+        let label = measurement.label();
+        let (text_width, _) = text_size(scale, &font, label.as_str());
+        let anchor = measurement.label_anchor();
+        let x = anchor.x as i32 - text_width / 2;
+        let y = anchor.y as i32;
+        draw_filled_rect_mut(
+            img,
+            imageproc::rect::Rect::at(x - padding, y)
+                .of_size((text_width + 2 * padding) as u32, (text_height + 2 * padding) as u32),
+            color,
+        );
+        draw_text_mut(img, contrast_color(color), x, y + padding, scale, &font, label.as_str());
+    }
+}
@@ -8,14 +8,40 @@ mod resizable_box;
 mod custom_slider;
 mod alert;
 mod shortcut_keys;
+mod text_style;
+mod step_marker;
+mod color_picker;
+mod annotation;
+mod project;
+mod stroke;
+mod spotlight;
+mod callout;
+mod measurement;
+mod watermark;
+mod export;
+mod adjustments;
+mod stitch;
+mod compare;
+mod scroll_capture;
 
 pub use colored_button::ColoredButton;
-pub use selected_rect::{SelectedRect,UPDATE_RECT_SIZE};
-pub use custom_zstack::{CustomZStack,OverImages,CREATE_ZSTACK,SAVE_OVER_IMG,SHOW_OVER_IMG,UPDATE_COLOR,UPDATE_BACK_IMG};
-pub use screenshot_image::{ScreenshotImage,UPDATE_SCREENSHOT,UPDATE_SCREENSHOT_CROP,UPDATE_SCREENSHOT_CROP_CLOSE};
-pub use take_screenshot_button::{TakeScreenshotButton,SAVE_SCREENSHOT};
-pub use resizable_box::{ResizableBox,UPDATE_ORIGIN};
+pub use selected_rect::{SelectedRect,UPDATE_RECT_SIZE,SET_CROP_ASPECT};
+pub use custom_zstack::{CustomZStack,OverImages,Orientation,ORIENT_SCREENSHOT,ANNOTATION_SELECTED,BLEND_MODE_CHANGED,SET_BLEND_MODE,SET_ANNOTATION_STYLE,ANNOTATION_STYLE_CHANGED,FLIP_OVER_IMG,SET_ZOOM,ZOOM_CHANGED,PAN_KEY,SET_SPOTLIGHT,SET_CALLOUT_CONNECTOR,CREATE_ZSTACK,SAVE_OVER_IMG,SHOW_OVER_IMG,UPDATE_COLOR,UPDATE_BACK_IMG,CROP_BACK_IMG,START_CROP,CANCEL_CROP,RESET_CROP,UPDATE_OVER_TEXT};
+pub use screenshot_image::{ScreenshotImage,AUTO_TRIM,UPDATE_SCREENSHOT,UPDATE_SCREENSHOT_CROP,UPDATE_SCREENSHOT_CROP_CLOSE};
+pub use take_screenshot_button::{TakeScreenshotButton,SAVE_SCREENSHOT,SCROLL_CAPTURE};
+pub use resizable_box::{ResizableBox,UPDATE_ORIGIN,UPDATE_ROTATION};
 pub use custom_slider::CustomSlider;
-pub use alert::{Alert};
+pub use alert::{Alert, SHOW_ALERT};
 pub use shortcut_keys::{ShortcutKeys, StateShortcutKeys, SHORTCUT_KEYS, read_from_file, write_to_file, verify_exists_dir};
-
+pub use text_style::TextStyle;
+pub use color_picker::{ColorPickerState, HexColor, HueBar, SaturationValueSquare, COLOR_PICKED, START_EYEDROPPER};
+pub use project::{Project, ProjectHeader, OPEN_PROJECT, PROJECT_EXTENSION, SAVE_PROJECT, LOAD_PROJECT, WRITE_PROJECT};
+pub use annotation::{AnnotationStyle, BlendMode};
+pub use stroke::StrokeShape;
+pub use spotlight::{SpotlightEffect, SpotlightSettings, SpotlightShape};
+pub use measurement::MeasureShape;
+pub use watermark::{WatermarkKind, WatermarkPosition, WatermarkSettings, SET_WATERMARK};
+pub use export::{is_save_format, keeps_alpha, save_image, Beautify, BeautifyBackground, EncodeSettings, ENCODE_QUALITY_RANGE, ExportScale, ResampleFilter, ScaleMode, SAVE_FORMATS, SET_BEAUTIFY, SET_ENCODE, SET_EXPORT_SCALE};
+pub use adjustments::{Adjustments, SET_ADJUSTMENTS};
+pub use stitch::{stitch, StitchBackground, StitchLayout, StitchSettings, SAVE_STITCH, SHOW_STITCH};
+pub use compare::{CompareController, CompareMode, CompareSettings, CompareSide, COMPARE_AFTER, COMPARE_BEFORE, COMPARE_CHANGED, LOAD_COMPARE_IMAGE, SET_COMPARE, SHOW_COMPARE, SHOW_COMPARE_ALERT};
\ No newline at end of file
@@ -0,0 +1,402 @@
+use std::io::Cursor;
+use std::time::{SystemTime, UNIX_EPOCH};
+use druid::{Color, FileInfo, Point, Rect, Selector};
+use image::{DynamicImage, ImageOutputFormat};
+use serde::{Deserialize, Serialize};
+use crate::custom_widget::annotation::{Annotation, AnnotationStyle, BlendMode, Transform};
+use crate::custom_widget::callout::Callout;
+use crate::custom_widget::measurement::{MeasureShape, Measurement};
+use crate::custom_widget::step_marker::StepMarker;
+use crate::custom_widget::spotlight::{SpotlightRegion, SpotlightSettings, SpotlightShape};
+use crate::custom_widget::watermark::CaptureInfo;
+use crate::custom_widget::adjustments::Adjustments;
+use crate::custom_widget::TextStyle;
+
+/// extension of the project files
+pub const PROJECT_EXTENSION: &str = "sgp";
+/// version of the project format, increased every time it changes: the version 2 writes the
+/// images in base64 and saves the blend modes, the insets, the styles, the rotations, the
+/// spotlight, the measurements, the capture info and the adjustments
+const PROJECT_VERSION: u32 = 2;
+
+pub const SAVE_PROJECT: Selector<FileInfo> = Selector::new("Save the project in the file chosen by the user");
+pub const OPEN_PROJECT: Selector<FileInfo> = Selector::new("Open the project file chosen by the user");
+pub const WRITE_PROJECT: Selector<(String, String, String)> =
+    Selector::new("Tell the ZStack to write the project, params: (project path, file name, image extension)");
+pub const LOAD_PROJECT: Selector<Project> = Selector::new("Tell the ZStack to restore a project, params: project");
+
+/// Information about the project, not needed to restore the edited image.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProjectMetadata {
+    pub(crate) version: u32,
+    /// name and extension used when the image is exported
+    pub(crate) name: String,
+    pub(crate) extension: String,
+    /// when the project has been saved, in seconds since the Unix epoch
+    pub(crate) saved_at: u64,
+    /// size of the original capture
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[derive(Deserialize)]
+struct VersionMetadata {
+    version: u32,
+}
+
+/// Only the version of a project file, read before the rest: a newer format may not be readable
+/// as a `Project`.
+#[derive(Deserialize)]
+pub struct ProjectHeader {
+    metadata: VersionMetadata,
+}
+
+impl ProjectHeader {
+    /// Checks if the project has been saved by a version of the application that this one can
+    /// read entirely.
+    pub fn is_supported(&self) -> bool {
+        self.metadata.version <= PROJECT_VERSION
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ProjectAnnotation {
+    over_img_index: usize,
+    /// the over-image, encoded as PNG
+    #[serde(with = "base64_bytes")]
+    image: Vec<u8>,
+    /// x0, y0, x1, y1 in pixels of the edited image
+    rect: [f64; 4],
+    text: Option<(String, TextStyle)>,
+    opaque: bool,
+    #[serde(default)]
+    blend: BlendMode,
+    #[serde(default)]
+    callout: Option<ProjectCallout>,
+    #[serde(default)]
+    style: Option<ProjectStyle>,
+    #[serde(default)]
+    transform: Transform,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ProjectStyle {
+    outline: bool,
+    outline_color: [u8; 4],
+    outline_width: f64,
+    shadow: bool,
+    shadow_offset: f64,
+    shadow_blur: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ProjectCallout {
+    source: [f64; 4],
+    color: [u8; 4],
+    connector: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ProjectMarker {
+    center: [f64; 2],
+    color: [u8; 4],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ProjectMeasurement {
+    start: [f64; 2],
+    end: [f64; 2],
+    shape: MeasureShape,
+    color: [u8; 4],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ProjectSpotlight {
+    settings: SpotlightSettings,
+    regions: Vec<([f64; 4], SpotlightShape)>,
+}
+
+/// The work on a screenshot, saved to be reopened later: the original capture plus everything
+/// that is kept apart from it until the export.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub(crate) metadata: ProjectMetadata,
+    /// the original capture, encoded as PNG
+    #[serde(with = "base64_bytes")]
+    capture: Vec<u8>,
+    /// x0, y0, x1, y1 of the edited part of the capture
+    crop: Option<[f64; 4]>,
+    annotations: Vec<ProjectAnnotation>,
+    markers: Vec<ProjectMarker>,
+    /// the selected color and the transparency (0-100) of the over-images
+    color: Option<[u8; 4]>,
+    alpha: f64,
+    #[serde(default)]
+    spotlight: Option<ProjectSpotlight>,
+    #[serde(default)]
+    measurements: Vec<ProjectMeasurement>,
+    #[serde(default)]
+    capture_info: Option<CaptureInfo>,
+    #[serde(default)]
+    adjustments: Adjustments,
+}
+
+/// The encoded images are written as base64 strings, the projects of version 1 have arrays of
+/// numbers, four times larger.
+mod base64_bytes {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Bytes {
+        Base64(String),
+        Array(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &Vec<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        match Bytes::deserialize(deserializer)? {
+            Bytes::Base64(text) => base64::decode(text).map_err(D::Error::custom),
+            Bytes::Array(bytes) => Ok(bytes),
+        }
+    }
+}
+
+fn encode_png(img: &DynamicImage) -> Vec<u8> {
+    let mut buffer = Cursor::new(Vec::new());
+    img.write_to(&mut buffer, ImageOutputFormat::Png)
+        .expect("Can't encode the image");
+    buffer.into_inner()
+}
+
+fn rect_to_array(rect: Rect) -> [f64; 4] {
+    [rect.x0, rect.y0, rect.x1, rect.y1]
+}
+
+fn array_to_rect(rect: [f64; 4]) -> Rect {
+    Rect::new(rect[0], rect[1], rect[2], rect[3])
+}
+
+fn color_to_array(color: Color) -> [u8; 4] {
+    let (r, g, b, a) = color.as_rgba8();
+    [r, g, b, a]
+}
+
+fn array_to_color(color: [u8; 4]) -> Color {
+    Color::rgba8(color[0], color[1], color[2], color[3])
+}
+
+impl Project {
+    pub fn new(
+        capture: &DynamicImage,
+        crop: Option<Rect>,
+        annotations: &[Annotation],
+        markers: &[StepMarker],
+        color: (Option<Color>, f64),
+        name: String,
+        extension: String,
+    ) -> Self {
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            metadata: ProjectMetadata {
+                version: PROJECT_VERSION,
+                name,
+                extension,
+                saved_at,
+                width: capture.width(),
+                height: capture.height(),
+            },
+            capture: encode_png(capture),
+            crop: crop.map(rect_to_array),
+            annotations: annotations
+                .iter()
+                .map(|annotation| ProjectAnnotation {
+                    over_img_index: annotation.over_img_index,
+                    image: encode_png(&annotation.image),
+                    rect: rect_to_array(annotation.rect),
+                    text: annotation.text.clone(),
+                    opaque: annotation.opaque,
+                    blend: annotation.blend,
+                    callout: annotation.callout.as_ref().map(|callout| ProjectCallout {
+                        source: rect_to_array(callout.source),
+                        color: color_to_array(callout.color),
+                        connector: callout.connector,
+                    }),
+                    style: Some(ProjectStyle {
+                        outline: annotation.style.outline,
+                        outline_color: color_to_array(annotation.style.outline_color),
+                        outline_width: annotation.style.outline_width,
+                        shadow: annotation.style.shadow,
+                        shadow_offset: annotation.style.shadow_offset,
+                        shadow_blur: annotation.style.shadow_blur,
+                    }),
+                    transform: annotation.transform,
+                })
+                .collect(),
+            markers: markers
+                .iter()
+                .map(|marker| ProjectMarker {
+                    center: [marker.center.x, marker.center.y],
+                    color: color_to_array(marker.color),
+                })
+                .collect(),
+            color: color.0.map(color_to_array),
+            alpha: color.1,
+            spotlight: None,
+            measurements: Vec::new(),
+            capture_info: None,
+            adjustments: Adjustments::default(),
+        }
+    }
+
+    /// Builder-style method to save the spotlight too.
+    pub fn with_spotlight(mut self, regions: &[SpotlightRegion], settings: SpotlightSettings) -> Self {
+        self.spotlight = Some(ProjectSpotlight {
+            settings,
+            regions: regions
+                .iter()
+                .map(|region| (rect_to_array(region.rect), region.shape))
+                .collect(),
+        });
+        self
+    }
+
+    /// Builder-style method to save the measurements too.
+    pub fn with_measurements(mut self, measurements: &[Measurement]) -> Self {
+        self.measurements = measurements
+            .iter()
+            .map(|measurement| ProjectMeasurement {
+                start: [measurement.start.x, measurement.start.y],
+                end: [measurement.end.x, measurement.end.y],
+                shape: measurement.shape,
+                color: color_to_array(measurement.color),
+            })
+            .collect();
+        self
+    }
+
+    /// Builder-style method to save when and where the screenshot has been taken.
+    pub fn with_capture_info(mut self, capture_info: CaptureInfo) -> Self {
+        self.capture_info = Some(capture_info);
+        self
+    }
+
+    /// Builder-style method to save the color adjustments too.
+    pub fn with_adjustments(mut self, adjustments: Adjustments) -> Self {
+        self.adjustments = adjustments;
+        self
+    }
+
+    /// The original capture, `None` if it can't be decoded.
+    pub fn capture(&self) -> Option<DynamicImage> {
+        image::load_from_memory(&self.capture).ok()
+    }
+
+    pub fn crop(&self) -> Option<Rect> {
+        self.crop.map(array_to_rect)
+    }
+
+    /// The annotations saved in the project, the ones that can't be decoded are skipped.
+    pub fn annotations(&self) -> Vec<Annotation> {
+        self.annotations
+            .iter()
+            .filter_map(|annotation| {
+                Some(Annotation {
+                    over_img_index: annotation.over_img_index,
+                    image: image::load_from_memory(&annotation.image).ok()?,
+                    rect: array_to_rect(annotation.rect),
+                    text: annotation.text.clone(),
+                    opaque: annotation.opaque,
+                    blend: annotation.blend,
+                    callout: annotation.callout.as_ref().map(|callout| Callout {
+                        source: array_to_rect(callout.source),
+                        color: array_to_color(callout.color),
+                        connector: callout.connector,
+                    }),
+                    style: annotation
+                        .style
+                        .as_ref()
+                        .map(|style| AnnotationStyle {
+                            outline: style.outline,
+                            outline_color: array_to_color(style.outline_color),
+                            outline_width: style.outline_width,
+                            shadow: style.shadow,
+                            shadow_offset: style.shadow_offset,
+                            shadow_blur: style.shadow_blur,
+                        })
+                        .unwrap_or_default(),
+                    transform: annotation.transform,
+                })
+            })
+            .collect()
+    }
+
+    pub fn markers(&self) -> Vec<StepMarker> {
+        self.markers
+            .iter()
+            .map(|marker| {
+                StepMarker::new(
+                    Point::new(marker.center[0], marker.center[1]),
+                    array_to_color(marker.color),
+                )
+            })
+            .collect()
+    }
+
+    pub fn measurements(&self) -> Vec<Measurement> {
+        self.measurements
+            .iter()
+            .map(|measurement| {
+                Measurement::new(
+                    Point::new(measurement.start[0], measurement.start[1]),
+                    Point::new(measurement.end[0], measurement.end[1]),
+                    measurement.shape,
+                    array_to_color(measurement.color),
+                )
+            })
+            .collect()
+    }
+
+    /// When and where the screenshot has been taken, the projects saved without it use the time
+    /// they have been saved.
+    pub fn capture_info(&self) -> CaptureInfo {
+        self.capture_info.unwrap_or(CaptureInfo {
+            captured_at: self.metadata.saved_at,
+            monitor: None,
+        })
+    }
+
+    pub fn adjustments(&self) -> Adjustments {
+        self.adjustments
+    }
+
+    pub fn color(&self) -> (Option<Color>, f64) {
+        (self.color.map(array_to_color), self.alpha)
+    }
+
+    /// The spotlight regions and settings, the default ones for the projects without spotlight.
+    pub fn spotlight(&self) -> (Vec<SpotlightRegion>, SpotlightSettings) {
+        match self.spotlight.as_ref() {
+            Some(spotlight) => (
+                spotlight
+                    .regions
+                    .iter()
+                    .map(|(rect, shape)| SpotlightRegion {
+                        rect: array_to_rect(*rect),
+                        shape: *shape,
+                    })
+                    .collect(),
+                spotlight.settings,
+            ),
+            None => (Vec::new(), SpotlightSettings::default()),
+        }
+    }
+}
@@ -4,12 +4,19 @@ use tracing::{instrument, trace, warn};
 use druid::widget::prelude::*;
 use druid::widget::{Axis};
 use druid::{Color, Cursor, Data, KeyOrValue, MouseEvent, Point, Rect, Selector, WidgetPod};
+use druid::kurbo::{Circle, Line};
 use druid::piet::{LineJoin, StrokeStyle};
+use crate::custom_widget::annotation::Transform;
 
 const BORDER_WIDTH:f64 = 2.;
 const DISTANCE_MARGIN:f64 = 10.0;
+/// radius of the handle dragged to rotate the content
+const ROTATION_HANDLE_RADIUS:f64 = 5.;
+/// the rotation snaps to multiples of this angle while Shift is pressed
+const ROTATION_SNAP:f64 = 15.;
 
 pub const UPDATE_ORIGIN:Selector<Point> = Selector::new("Tell the customZStack to update the resizableBox origin");
+pub const UPDATE_ROTATION:Selector<f64> = Selector::new("Tell the customZStack the content of the resizableBox has been rotated, params: clockwise angle in degrees");
 
 #[derive(Copy, Clone, PartialEq)]
 enum IfMousePressedWhere {
@@ -41,7 +48,12 @@ pub struct ResizableBox<T> {
     mouse: IfMousePressedWhere,
     rect: Option<Rect>,
     new_origin: Option<Point>,
-    father_id: WidgetId
+    start_origin: Point,
+    father_id: WidgetId,
+    /// how the content is mirrored and rotated around the center of the box
+    transform: Transform,
+    /// the angle of the mouse around the center and the rotation when the handle has been pressed
+    rotating: Option<(f64, f64)>
 }
 
 #[allow(dead_code)]
@@ -55,10 +67,25 @@ impl<T> ResizableBox<T> {
             mouse: IfMousePressedWhere::NotInterested,
             rect: None,
             new_origin: None,
-            father_id
+            start_origin: Point::ZERO,
+            father_id,
+            transform: Transform::default(),
+            rotating: None
         }
     }
 
+    /// Set how the content is mirrored and rotated.
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set the origin where the box has already been moved inside its father.
+    pub fn with_origin(mut self, origin: Point) -> Self {
+        self.start_origin = origin;
+        self
+    }
+
     /// Set container's width.
     pub fn width(mut self, width: impl Into<KeyOrValue<f64>>) -> Self {
         self.width = Some(width.into());
@@ -128,39 +155,53 @@ impl<T> ResizableBox<T> {
         )
     }
 
+    /// Finds the side or the corner of the box under the mouse, following its rotation; the
+    /// position of the mouse is kept as it is.
     fn where_mouse_is(self: &Self, me:&MouseEvent) -> IfMousePressedWhere{
         let pos = me.pos;
-        let x0 = self.rect.unwrap().x0;
-        let x1 = self.rect.unwrap().x1;
-        let y0 = self.rect.unwrap().y0;
-        let y1 = self.rect.unwrap().y1;
-        return if f64::abs(pos.x - x0) < DISTANCE_MARGIN {
-            if f64::abs(pos.y - y0) < DISTANCE_MARGIN{
+        let rect = self.rect.unwrap();
+        let local = self.transform.affine(rect).inverse() * pos;
+        let (x0, x1, y0, y1) = (rect.x0, rect.x1, rect.y0, rect.y1);
+        return if f64::abs(local.x - x0) < DISTANCE_MARGIN {
+            if f64::abs(local.y - y0) < DISTANCE_MARGIN{
                 IfMousePressedWhere::NorthWest(pos)
-            } else if f64::abs(pos.y - y1) < DISTANCE_MARGIN{
+            } else if f64::abs(local.y - y1) < DISTANCE_MARGIN{
                 IfMousePressedWhere::SouthWest(pos)
             } else {
                 IfMousePressedWhere::West(pos)
             }
-        } else if f64::abs(pos.x - x1) < DISTANCE_MARGIN {
-            if f64::abs(pos.y - y0) < DISTANCE_MARGIN{
+        } else if f64::abs(local.x - x1) < DISTANCE_MARGIN {
+            if f64::abs(local.y - y0) < DISTANCE_MARGIN{
                 IfMousePressedWhere::NorthEst(pos)
-            } else if f64::abs(pos.y - y1) < DISTANCE_MARGIN{
+            } else if f64::abs(local.y - y1) < DISTANCE_MARGIN{
                 IfMousePressedWhere::SouthEst(pos)
             } else {
                 IfMousePressedWhere::Est(pos)
             }
-        } else if f64::abs(pos.y - y0) < DISTANCE_MARGIN{
+        } else if f64::abs(local.y - y0) < DISTANCE_MARGIN{
             IfMousePressedWhere::North(pos)
-        } else if f64::abs(pos.y - y1) < DISTANCE_MARGIN{
+        } else if f64::abs(local.y - y1) < DISTANCE_MARGIN{
             IfMousePressedWhere::South(pos)
-        } else if pos.y > y0 && pos.y < y1 && pos.x > x0 && pos.x < x1{
+        } else if local.y > y0 && local.y < y1 && local.x > x0 && local.x < x1{
             IfMousePressedWhere::Inside(pos)
         } else {
             IfMousePressedWhere::NotInterested
         }
     }
 
+    /// Where the rotation handle is: near the top side, turned together with the content.
+    fn rotation_handle(self: &Self) -> Option<Point> {
+        let rect = self.rect?;
+        let handle = Point::new(rect.center().x, rect.y0 + DISTANCE_MARGIN + ROTATION_HANDLE_RADIUS);
+        Some(self.transform.affine(rect) * handle)
+    }
+
+    /// The angle of the point around the center of the box, in degrees.
+    fn angle_around_center(self: &Self, pos: Point) -> f64 {
+        let center = self.rect.unwrap().center();
+        (pos.y - center.y).atan2(pos.x - center.x).to_degrees()
+    }
+
     fn set_rect(&mut self, origin: Point ,size: Size){
         let rect =Rect::new(
             origin.x,
@@ -171,7 +212,7 @@ impl<T> ResizableBox<T> {
         self.width = Some(KeyOrValue::Concrete(rect.width()));
         self.height = Some(KeyOrValue::Concrete(rect.height()));
         self.rect = Some(rect);
-        self.new_origin = Some(origin);
+        self.new_origin = Some(origin + self.start_origin.to_vec2());
     }
 }
 
@@ -185,87 +226,120 @@ impl<T: Data> Widget<T> for ResizableBox<T> {
             }
             Event::MouseDown(me) => {
                 ctx.set_active(true);
-                self.mouse = self.where_mouse_is(me);
+                match self.rotation_handle() {
+                    Some(handle) if handle.distance(me.pos) <= ROTATION_HANDLE_RADIUS + 2. => {
+                        self.rotating = Some((self.angle_around_center(me.pos), self.transform.rotation));
+                        self.mouse = IfMousePressedWhere::NotInterested;
+                    }
+                    _ => self.mouse = self.where_mouse_is(me)
+                }
+                ctx.request_paint();
+            }
+            Event::MouseMove(me) if self.rotating.is_some() => {
+                let (start_angle, start_rotation) = self.rotating.unwrap();
+                let mut rotation = start_rotation + self.angle_around_center(me.pos) - start_angle;
+                if me.mods.shift() {
+                    rotation = (rotation / ROTATION_SNAP).round() * ROTATION_SNAP;
+                }
+                self.transform.rotation = rotation.rem_euclid(360.);
                 ctx.request_paint();
             }
             Event::MouseMove(me) => {
                 if self.mouse != IfMousePressedWhere::NotInterested { //if the mouse has been pressed
                     let pos = me.pos;
                     let mut rect = self.rect.unwrap();
+                    let old_rect = rect;
+                    let old_pos = match self.mouse {
+                        IfMousePressedWhere::NotInterested => pos,
+                        IfMousePressedWhere::North(old_pos)
+                        | IfMousePressedWhere::NorthEst(old_pos)
+                        | IfMousePressedWhere::Est(old_pos)
+                        | IfMousePressedWhere::SouthEst(old_pos)
+                        | IfMousePressedWhere::South(old_pos)
+                        | IfMousePressedWhere::SouthWest(old_pos)
+                        | IfMousePressedWhere::West(old_pos)
+                        | IfMousePressedWhere::NorthWest(old_pos)
+                        | IfMousePressedWhere::Inside(old_pos) => old_pos,
+                    };
+                    // the sides are dragged along the rotated box: the movement of the mouse is
+                    // turned back into it
+                    let d = self.transform.affine(Rect::ZERO).inverse() * (pos - old_pos).to_point();
                     match self.mouse {
                         IfMousePressedWhere::NotInterested => (),
-                        IfMousePressedWhere::North(old_pos) => {
-                            rect.x0 += pos.y - old_pos.y;
-                            rect.y0 += pos.y - old_pos.y;
+                        IfMousePressedWhere::North(_) => {
+                            rect.x0 += d.y;
+                            rect.y0 += d.y;
                             self.mouse = IfMousePressedWhere::North(pos);
                         }
-                        IfMousePressedWhere::NorthEst(old_pos) => {
-                            if f64::abs(pos.x - old_pos.x) > f64::abs(pos.y - old_pos.y){
-                                let dif = pos.x - old_pos.x;
-                                rect.y0 -= dif;
-                                rect.x1 += dif;
+                        IfMousePressedWhere::NorthEst(_) => {
+                            if f64::abs(d.x) > f64::abs(d.y){
+                                rect.y0 -= d.x;
+                                rect.x1 += d.x;
                             } else {
-                                let dif = pos.y - old_pos.y;
-                                rect.y0 += dif;
-                                rect.x1 -= dif;
+                                rect.y0 += d.y;
+                                rect.x1 -= d.y;
                             };
                             self.mouse = IfMousePressedWhere::NorthEst(pos);
                         }
-                        IfMousePressedWhere::Est(old_pos) => {
-                            rect.y1 += pos.x - old_pos.x;
-                            rect.x1 = pos.x;
+                        IfMousePressedWhere::Est(_) => {
+                            rect.y1 += d.x;
+                            rect.x1 += d.x;
                             self.mouse = IfMousePressedWhere::Est(pos);
                         }
-                        IfMousePressedWhere::SouthEst(old_pos) => {
-                            let dif = if f64::abs(pos.x - old_pos.x) > f64::abs(pos.y - old_pos.y){
-                                pos.x - old_pos.x
+                        IfMousePressedWhere::SouthEst(_) => {
+                            let dif = if f64::abs(d.x) > f64::abs(d.y){
+                                d.x
                             } else {
-                                pos.y - old_pos.y
+                                d.y
                             };
                             rect.y1 += dif;
                             rect.x1 += dif;
                             self.mouse = IfMousePressedWhere::SouthEst(pos);
                         }
-                        IfMousePressedWhere::South(old_pos) => {
-                            rect.x1 += pos.y - old_pos.y;
-                            rect.y1 = pos.y;
+                        IfMousePressedWhere::South(_) => {
+                            rect.x1 += d.y;
+                            rect.y1 += d.y;
                             self.mouse = IfMousePressedWhere::South(pos);
                         }
-                        IfMousePressedWhere::SouthWest(old_pos) => {
-                            if f64::abs(pos.x - old_pos.x) > f64::abs(pos.y - old_pos.y){
-                                let dif = pos.x - old_pos.x;
-                                rect.y1 -= dif;
-                                rect.x0 += dif;
+                        IfMousePressedWhere::SouthWest(_) => {
+                            if f64::abs(d.x) > f64::abs(d.y){
+                                rect.y1 -= d.x;
+                                rect.x0 += d.x;
                             } else {
-                                let dif = pos.y - old_pos.y;
-                                rect.y1 += dif;
-                                rect.x0 -= dif;
+                                rect.y1 += d.y;
+                                rect.x0 -= d.y;
                             };
                             self.mouse = IfMousePressedWhere::SouthWest(pos);
                         }
-                        IfMousePressedWhere::West(old_pos) => {
-                            rect.y0 += pos.x - old_pos.x;
-                            rect.x0 = pos.x;
+                        IfMousePressedWhere::West(_) => {
+                            rect.y0 += d.x;
+                            rect.x0 += d.x;
                             self.mouse = IfMousePressedWhere::West(pos);
                         }
-                        IfMousePressedWhere::NorthWest(old_pos) => {
-                            let dif = if f64::abs(pos.x - old_pos.x) > f64::abs(pos.y - old_pos.y){
-                                pos.x - old_pos.x
+                        IfMousePressedWhere::NorthWest(_) => {
+                            let dif = if f64::abs(d.x) > f64::abs(d.y){
+                                d.x
                             } else {
-                                pos.y - old_pos.y
+                                d.y
                             };
                             rect.y0 += dif;
                             rect.x0 += dif;
                             self.mouse = IfMousePressedWhere::NorthWest(pos);
                         }
-                        IfMousePressedWhere::Inside(old_pos) => {
-                            rect.y0 += pos.y - old_pos.y;
-                            rect.y1 += pos.y - old_pos.y;
-                            rect.x0 += pos.x - old_pos.x;
-                            rect.x1 += pos.x - old_pos.x;
+                        IfMousePressedWhere::Inside(_) => {
+                            rect = rect + (pos - old_pos);
                             self.mouse = IfMousePressedWhere::Inside(pos);
                         }
                     }
+                    if !matches!(self.mouse, IfMousePressedWhere::Inside(_)) {
+                        // the box turns around its center, that moves while resizing: the corner
+                        // opposite to the dragged one must stay where it is shown
+                        let anchor = Point::new(
+                            if rect.x0 == old_rect.x0 { rect.x0 } else { rect.x1 },
+                            if rect.y0 == old_rect.y0 { rect.y0 } else { rect.y1 },
+                        );
+                        rect = rect + (self.transform.affine(old_rect) * anchor - self.transform.affine(rect) * anchor);
+                    }
 
 
                     //Keeps validity
@@ -330,13 +404,25 @@ impl<T: Data> Widget<T> for ResizableBox<T> {
                         IfMousePressedWhere::NorthWest(_) => {
                             ctx.override_cursor(&Cursor::Crosshair);
                         }
-                        _ => ctx.clear_cursor()
+                        _ => match self.rotation_handle() {
+                            Some(handle) if handle.distance(me.pos) <= ROTATION_HANDLE_RADIUS + 2. => {
+                                ctx.override_cursor(&Cursor::Pointer);
+                            }
+                            _ => ctx.clear_cursor()
+                        }
                     }
                 }
             }
             Event::MouseUp(_)=>{
                 self.mouse = IfMousePressedWhere::NotInterested;
                 ctx.set_active(false);
+                if self.rotating.take().is_some() {
+                    ctx.submit_command(
+                        UPDATE_ROTATION
+                            .with(self.transform.rotation)
+                            .to(druid::Target::Widget(self.father_id))
+                    );
+                }
                 if self.rect.is_some() {
                     let rect = self.rect.unwrap();
                     let mut new_origin = self.new_origin.unwrap();
@@ -444,11 +530,22 @@ impl<T: Data> Widget<T> for ResizableBox<T> {
             .line_join(LineJoin::Round)
             .line_cap(Default::default())
             .dash_offset(0.0);
-        ctx.stroke_styled(rect, &border_color, BORDER_WIDTH, &style);
+        let affine = self.transform.affine(rect);
+        let handle = Point::new(rect.center().x, rect.y0 + DISTANCE_MARGIN + ROTATION_HANDLE_RADIUS);
+        let child = self.child.as_mut();
+        ctx.with_save(|ctx| {
+            ctx.transform(affine);
+            ctx.stroke_styled(rect, &border_color, BORDER_WIDTH, &style);
+
+            if let Some(child) = child {
+                child.paint(ctx, data, env);
+            }
 
-        if let Some(ref mut child) = self.child {
-            child.paint(ctx, data, env);
-        }
+            // the rotation handle, joined to the top side
+            ctx.stroke(Line::new((rect.center().x, rect.y0), handle), &border_color, 1.);
+            ctx.fill(Circle::new(handle, ROTATION_HANDLE_RADIUS), &Color::WHITE);
+            ctx.stroke(Circle::new(handle, ROTATION_HANDLE_RADIUS), &border_color, 1.);
+        });
     }
 
     fn id(&self) -> Option<WidgetId> {
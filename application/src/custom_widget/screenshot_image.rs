@@ -1,15 +1,15 @@
 use std::sync::Arc;
-use druid::{kurbo::Rect, piet::{Image as _, ImageBuf, InterpolationMode, PietImage}, widget::prelude::*, Data, Selector};
+use druid::{kurbo::Rect, piet::{Image as _, ImageBuf, InterpolationMode, PietImage}, widget::prelude::*, Data, Selector, Target};
 use druid::piet::ImageFormat;
 use druid::widget::FillStrat;
-use image::DynamicImage;
+use image::{DynamicImage, Rgba, RgbaImage};
 use tracing::{instrument, trace};
-use crate::BASE_PATH_SCREENSHOT;
-use crate::custom_widget::{UPDATE_BACK_IMG, UPDATE_RECT_SIZE, verify_exists_dir};
+use crate::custom_widget::{CROP_BACK_IMG, SHOW_ALERT, UPDATE_RECT_SIZE};
 
 pub const UPDATE_SCREENSHOT: Selector<Arc<DynamicImage>> = Selector::new("Update the screenshot image");
-pub const UPDATE_SCREENSHOT_CROP: Selector<(Rect, Box<str>, Box<str>, image::ImageFormat, WidgetId)> = Selector::new("Update the screenshot image cropped");
+pub const UPDATE_SCREENSHOT_CROP: Selector<(Rect, WidgetId)> = Selector::new("Tell the ZStack which part of the shown screenshot is kept, params: (selected rect, ZStack id)");
 pub const UPDATE_SCREENSHOT_CROP_CLOSE: Selector<> = Selector::new("Update the rect size after closing");
+pub const AUTO_TRIM: Selector<(f64, WidgetId)> = Selector::new("Crop away the borders of uniform color of the shown screenshot, params: (tolerance from 0 to 100, ZStack id)");
 pub struct ScreenshotImage {
     image_data: ImageBuf,
     image_data_arc: Option<Arc<DynamicImage>>,
@@ -117,6 +117,63 @@ impl ScreenshotImage {
         self.image_data_arc = Some(image_data_arc);
         self.invalidate();
     }
+
+    /// Where the image is painted in a widget of the given size, following the fill strategy.
+    pub fn painted_rect(&mut self, size: Size) -> Rect {
+        let image_size = self.image_size();
+        self.fill
+            .affine_to_fill(size, image_size)
+            .transform_rect_bbox(image_size.to_rect())
+    }
+}
+
+/// Checks if the color of every channel differs at most by `max_diff` from the reference one.
+fn similar(px: &Rgba<u8>, reference: &Rgba<u8>, max_diff: u8) -> bool {
+    px.0.iter()
+        .zip(reference.0.iter())
+        .all(|(a, b)| a.abs_diff(*b) <= max_diff)
+}
+
+/// Finds the part of the image left after removing, from every side, the rows and the columns of
+/// the same color as the outermost pixel of that side.
+///
+/// Returns `None` if there is nothing to remove, or if the whole image has a uniform color.
+fn trim_borders(img: &RgbaImage, max_diff: u8) -> Option<Rect> {
+    let (mut x0, mut y0, mut x1, mut y1) = (0, 0, img.width(), img.height());
+    let uniform_row = |y: u32, x0: u32, x1: u32, reference: &Rgba<u8>| {
+        (x0..x1).all(|x| similar(img.get_pixel(x, y), reference, max_diff))
+    };
+    let uniform_column = |x: u32, y0: u32, y1: u32, reference: &Rgba<u8>| {
+        (y0..y1).all(|y| similar(img.get_pixel(x, y), reference, max_diff))
+    };
+
+    if x1 == 0 || y1 == 0 {
+        return None;
+    }
+    let top = *img.get_pixel(0, 0);
+    while y0 < y1 && uniform_row(y0, x0, x1, &top) {
+        y0 += 1;
+    }
+    if y0 == y1 {
+        return None;
+    }
+    let bottom = *img.get_pixel(0, y1 - 1);
+    while y1 > y0 && uniform_row(y1 - 1, x0, x1, &bottom) {
+        y1 -= 1;
+    }
+    let left = *img.get_pixel(0, y0);
+    while x0 < x1 && uniform_column(x0, y0, y1, &left) {
+        x0 += 1;
+    }
+    let right = *img.get_pixel(x1 - 1, y0);
+    while x1 > x0 && uniform_column(x1 - 1, y0, y1, &right) {
+        x1 -= 1;
+    }
+
+    if x0 == x1 || (x0, y0, x1, y1) == (0, 0, img.width(), img.height()) {
+        return None;
+    }
+    Some(Rect::new(x0 as f64, y0 as f64, x1 as f64, y1 as f64))
 }
 
 impl<T: Data> Widget<T> for ScreenshotImage {
@@ -146,14 +203,14 @@ impl<T: Data> Widget<T> for ScreenshotImage {
                     ctx.request_paint();
                 }
                 if cmd.is(UPDATE_SCREENSHOT_CROP) {
-                    let (rect_crop, path,file_name,file_format, custom_zstack_id) = cmd.get_unchecked(UPDATE_SCREENSHOT_CROP);
+                    let (rect_crop, custom_zstack_id) = cmd.get_unchecked(UPDATE_SCREENSHOT_CROP);
                     let screen_img = self.image_data_arc.clone().unwrap();
 
                     // it checks if the rect is inside the size of the image
-                    if rect_crop.width() as u32 >= (*screen_img).width()
-                        || rect_crop.height() as u32 >= (*screen_img).height()
-                        || rect_crop.x1 as u32 >= (*screen_img).width()
-                        || rect_crop.y1 as u32 >= (*screen_img).height()
+                    if rect_crop.width() as u32 > (*screen_img).width()
+                        || rect_crop.height() as u32 > (*screen_img).height()
+                        || rect_crop.x1 as u32 > (*screen_img).width()
+                        || rect_crop.y1 as u32 > (*screen_img).height()
                         || rect_crop.x0 as u32 >= (*screen_img).width()
                         || rect_crop.y0 as u32 >= (*screen_img).height()
                     {
@@ -167,38 +224,29 @@ impl<T: Data> Widget<T> for ScreenshotImage {
                         return;
                     }
 
-                    let img_resized = (*screen_img).clone().crop(rect_crop.x0 as u32, rect_crop.y0 as u32, rect_crop.x1 as u32, rect_crop.y1 as u32);
-
-                    let screen_img_rect = Rect {
-                        x0: 0.,
-                        y0: 0.,
-                        x1: img_resized.width() as f64,
-                        y1: img_resized.height() as f64
-                    };
-                    ctx.submit_command(UPDATE_RECT_SIZE.with(screen_img_rect)); // reset of the rect
-
-                    self.set_image_data(ImageBuf::from_raw(
-                        Arc::<[u8]>::from(img_resized.as_bytes()),
-                        ImageFormat::RgbaSeparate,
-                        img_resized.width() as usize,
-                        img_resized.height() as usize,
-                    ));
-
-                    // it verify if exists the dir before saving the image
-                    verify_exists_dir(BASE_PATH_SCREENSHOT);
-
-                    let path = format!("{}{}.{}", path, file_name, file_format.extensions_str().first().unwrap());
-                    img_resized.save_with_format(path.clone(), *file_format).unwrap();
-
-                    let img = Arc::new(img_resized);
-                    self.set_image_data_arc(img.clone());
-
-                    ctx.get_external_handle()
-                        .submit_command(UPDATE_BACK_IMG, img, *custom_zstack_id)
-                        .expect("Error sending the event to the screenshot widget");
-
-                    ctx.request_layout();
-                    ctx.request_paint();
+                    // the ZStack keeps the crop apart from the original capture until the export,
+                    // it sends back the cropped screenshot
+                    let kept_rect = Rect::new(
+                        rect_crop.x0.floor(),
+                        rect_crop.y0.floor(),
+                        rect_crop.x1.floor(),
+                        rect_crop.y1.floor(),
+                    );
+                    let screen_img_size = Size::new(screen_img.width() as f64, screen_img.height() as f64);
+                    ctx.submit_command(CROP_BACK_IMG.with((kept_rect, screen_img_size)).to(Target::Widget(*custom_zstack_id)));
+                }
+                if cmd.is(AUTO_TRIM) {
+                    let (tolerance, custom_zstack_id) = cmd.get_unchecked(AUTO_TRIM);
+                    if let Some(screen_img) = self.image_data_arc.clone() {
+                        let max_diff = (tolerance.clamp(0., 100.) * 255. / 100.).round() as u8;
+                        match trim_borders(&screen_img.to_rgba8(), max_diff) {
+                            Some(kept_rect) => {
+                                let screen_img_size = Size::new(screen_img.width() as f64, screen_img.height() as f64);
+                                ctx.submit_command(CROP_BACK_IMG.with((kept_rect, screen_img_size)).to(Target::Widget(*custom_zstack_id)));
+                            }
+                            None => ctx.submit_command(SHOW_ALERT.with("No uniform border has been found!".to_string())),
+                        }
+                    }
                 }
                 if cmd.is(UPDATE_SCREENSHOT_CROP_CLOSE) {
                     match self.image_data_arc.clone() {
@@ -0,0 +1,245 @@
+use std::time::Duration;
+use image::{DynamicImage, RgbaImage};
+
+/// time between two frames of a scrolling capture
+pub const SCROLL_FRAME_INTERVAL: Duration = Duration::from_millis(250);
+/// the scrolling capture stops when the page has not moved for this many frames
+pub const SCROLL_IDLE_FRAMES: u32 = 12;
+/// the scrolling capture stops when the image gets this tall, in pixels
+pub const SCROLL_MAX_HEIGHT: u32 = 20000;
+/// the scrolling capture gives up when the page doesn't start moving for this many frames
+pub const SCROLL_START_FRAMES: u32 = 40;
+/// how often the key that stops the scrolling capture is checked
+pub const SCROLL_KEY_POLL_INTERVAL: Duration = Duration::from_millis(25);
+/// rows two frames must share to be joined
+const SCROLL_MIN_OVERLAP: usize = 16;
+/// part of the shared rows that must be equal, the others can be changed by a sticky header or an
+/// animation
+const SCROLL_MATCH_RATIO: f64 = 0.9;
+/// columns on the right of the frames that are not compared, where the scrollbar moves
+const SCROLL_IGNORED_MARGIN: u32 = 24;
+/// the compared columns of a row are split in this many blocks, compared by their average color
+const SCROLL_ROW_BLOCKS: u32 = 32;
+/// how much the average of a block can change between two frames, because of a caret or a hover
+/// effect
+const SCROLL_BLOCK_TOLERANCE: f32 = 4.;
+/// part of the blocks of a row that must be equal for the row to match
+const SCROLL_ROW_MATCH_RATIO: f64 = 0.9;
+
+/// What happened to a frame of a scrolling capture.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FrameResult {
+    /// the page has not moved
+    Unchanged,
+    /// the new rows at the bottom of the frame have been added to the image
+    Appended(u32),
+    /// the frame shares no rows with the previous one, because the page moved too far or scrolled
+    /// up: it has been skipped
+    NoOverlap,
+}
+
+/// Joins the frames of a page scrolled down into a single tall image.
+pub struct ScrollStitcher {
+    width: u32,
+    /// the rows of the tall image, in RGBA
+    raw: Vec<u8>,
+    /// the rows of the frame the next one is compared to
+    last_rows: Vec<Vec<f32>>,
+    /// frames skipped after the last one that has been joined
+    pub(crate) trailing_skipped: u32,
+}
+
+/// The average color of the blocks of every row of the image, and whether the row has more than
+/// one color: the uniform rows match anywhere, they don't tell how much the page moved.
+///
+/// The margin on the right is left out, so that a moving scrollbar doesn't change the rows.
+fn row_blocks(img: &RgbaImage) -> (Vec<Vec<f32>>, Vec<bool>) {
+    let width = if img.width() > 2 * SCROLL_IGNORED_MARGIN {
+        img.width() - SCROLL_IGNORED_MARGIN
+    } else {
+        img.width()
+    };
+    let blocks = SCROLL_ROW_BLOCKS.min(width).max(1);
+    (0..img.height())
+        .map(|y| {
+            let mut averages = Vec::with_capacity(blocks as usize);
+            let (mut darkest, mut brightest) = (f32::MAX, f32::MIN);
+            for block in 0..blocks {
+                let (x0, x1) = (block * width / blocks, (block + 1) * width / blocks);
+                let mut sum = 0.;
+                for x in x0..x1 {
+                    let px = img.get_pixel(x, y);
+                    let value = (px[0] as f32 + px[1] as f32 + px[2] as f32) / 3.;
+                    darkest = darkest.min(value);
+                    brightest = brightest.max(value);
+                    sum += value;
+                }
+                averages.push(sum / (x1 - x0).max(1) as f32);
+            }
+            (averages, brightest - darkest > SCROLL_BLOCK_TOLERANCE)
+        })
+        .unzip()
+}
+
+/// Whether the two rows are the same, apart from a few blocks.
+fn rows_match(row: &[f32], other: &[f32]) -> bool {
+    let equal = row
+        .iter()
+        .zip(other)
+        .filter(|(block, other_block)| (*block - *other_block).abs() <= SCROLL_BLOCK_TOLERANCE)
+        .count();
+    equal as f64 >= row.len() as f64 * SCROLL_ROW_MATCH_RATIO
+}
+
+/// Finds how many rows the page moved down between the two frames, `None` if they share too few
+/// rows.
+fn find_offset(last: &[Vec<f32>], rows: &[Vec<f32>], distinctive: &[bool]) -> Option<usize> {
+    let height = rows.len().min(last.len());
+    let mut best: Option<(f64, usize)> = None;
+    for offset in 0..height.saturating_sub(SCROLL_MIN_OVERLAP) {
+        let (mut compared, mut matched) = (0, 0);
+        for i in 0..height - offset {
+            if distinctive[i] {
+                compared += 1;
+                if rows_match(&rows[i], &last[i + offset]) {
+                    matched += 1;
+                }
+            }
+        }
+        if compared < SCROLL_MIN_OVERLAP {
+            continue;
+        }
+        let ratio = matched as f64 / compared as f64;
+        if ratio >= SCROLL_MATCH_RATIO && best.map_or(true, |(best_ratio, _)| ratio > best_ratio) {
+            best = Some((ratio, offset));
+        }
+    }
+    best.map(|(_, offset)| offset)
+}
+
+impl ScrollStitcher {
+    pub fn new(first: RgbaImage) -> Self {
+        let (last_rows, _) = row_blocks(&first);
+        Self {
+            width: first.width(),
+            raw: first.into_raw(),
+            last_rows,
+            trailing_skipped: 0,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        (self.raw.len() / (self.width as usize * 4).max(1)) as u32
+    }
+
+    /// Adds the rows of the frame that were not visible in the previous one.
+    pub fn push(&mut self, frame: RgbaImage) -> FrameResult {
+        if frame.width() != self.width {
+            self.trailing_skipped += 1;
+            return FrameResult::NoOverlap;
+        }
+        let (rows, distinctive) = row_blocks(&frame);
+        let result = match find_offset(&self.last_rows, &rows, &distinctive) {
+            Some(0) => FrameResult::Unchanged,
+            Some(offset) => {
+                let row_len = self.width as usize * 4;
+                let new_rows = &frame.as_raw()[(rows.len() - offset) * row_len..];
+                self.raw.extend_from_slice(new_rows);
+                self.last_rows = rows;
+                FrameResult::Appended(offset as u32)
+            }
+            None => FrameResult::NoOverlap,
+        };
+        if result == FrameResult::NoOverlap {
+            self.trailing_skipped += 1;
+        } else {
+            self.trailing_skipped = 0;
+        }
+        result
+    }
+
+    pub fn into_image(self) -> DynamicImage {
+        let height = self.height();
+        DynamicImage::ImageRgba8(
+            RgbaImage::from_raw(self.width, height, self.raw).expect("The rows have the width of the frames"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{imageops, Rgba};
+
+    const WIDTH: u32 = 200;
+    const FRAME_HEIGHT: u32 = 120;
+
+    /// A page with a different pattern on every row, made of stripes a few pixels wide.
+    fn page(height: u32) -> RgbaImage {
+        RgbaImage::from_fn(WIDTH, height, |x, y| {
+            let seed = (y.wrapping_mul(2654435761) ^ (x / 6).wrapping_mul(40503)).wrapping_mul(2246822519);
+            let value = (seed >> 24) as u8;
+            Rgba([value, value.wrapping_mul(3), 255 - value, 255])
+        })
+    }
+
+    /// The part of the page shown when it is scrolled down by `top` rows.
+    fn frame(page: &RgbaImage, top: u32) -> RgbaImage {
+        imageops::crop_imm(page, 0, top, WIDTH, FRAME_HEIGHT).to_image()
+    }
+
+    #[test]
+    fn joins_the_frames_of_a_page_scrolled_down() {
+        let page = page(400);
+        let mut stitcher = ScrollStitcher::new(frame(&page, 0));
+        assert_eq!(stitcher.push(frame(&page, 30)), FrameResult::Appended(30));
+        assert_eq!(stitcher.push(frame(&page, 75)), FrameResult::Appended(45));
+        assert_eq!(stitcher.height(), FRAME_HEIGHT + 75);
+        let joined = stitcher.into_image().into_rgba8();
+        assert_eq!(joined, imageops::crop_imm(&page, 0, 0, WIDTH, FRAME_HEIGHT + 75).to_image());
+    }
+
+    #[test]
+    fn a_page_that_has_not_moved_is_unchanged() {
+        let page = page(200);
+        let mut stitcher = ScrollStitcher::new(frame(&page, 10));
+        assert_eq!(stitcher.push(frame(&page, 10)), FrameResult::Unchanged);
+        assert_eq!(stitcher.height(), FRAME_HEIGHT);
+    }
+
+    #[test]
+    fn ignores_the_scrollbar_and_a_caret() {
+        let page = page(400);
+        let with_scrollbar = |top: u32| {
+            let mut frame = frame(&page, top);
+            // the scrollbar thumb moves with the page
+            for y in 0..FRAME_HEIGHT {
+                for x in WIDTH - 12..WIDTH {
+                    let thumb = y >= top / 4 && y < top / 4 + 30;
+                    frame.put_pixel(x, y, if thumb { Rgba([90, 90, 90, 255]) } else { Rgba([240, 240, 240, 255]) });
+                }
+            }
+            frame
+        };
+        let mut stitcher = ScrollStitcher::new(with_scrollbar(0));
+        let mut next = with_scrollbar(40);
+        // a blinking caret shown only in the second frame
+        for y in 20..40 {
+            next.put_pixel(50, y, Rgba([0, 0, 0, 255]));
+        }
+        assert_eq!(stitcher.push(next), FrameResult::Appended(40));
+        assert_eq!(stitcher.trailing_skipped, 0);
+    }
+
+    #[test]
+    fn skips_a_frame_that_moved_too_far() {
+        let page = page(400);
+        let mut stitcher = ScrollStitcher::new(frame(&page, 0));
+        assert_eq!(stitcher.push(frame(&page, FRAME_HEIGHT + 10)), FrameResult::NoOverlap);
+        assert_eq!(stitcher.trailing_skipped, 1);
+        assert_eq!(stitcher.height(), FRAME_HEIGHT);
+        // the next frame is compared to the last joined one
+        assert_eq!(stitcher.push(frame(&page, 50)), FrameResult::Appended(50));
+        assert_eq!(stitcher.trailing_skipped, 0);
+    }
+}
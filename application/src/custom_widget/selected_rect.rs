@@ -8,6 +8,10 @@ const DISTANCE_MARGIN: f64 = 10.0;
 const BORDER_WIDTH: f64 = 5.;
 
 pub const UPDATE_RECT_SIZE: Selector<Rect> = Selector::new("Update the rect size");
+pub const SET_CROP_ASPECT: Selector<Option<f64>> =
+    Selector::new("Tell the SelectedRect the ratio between width and height of the selection, params: ratio, None for a free selection");
+pub const SET_CROP_SELECTION: Selector<Rect> =
+    Selector::new("Tell the SelectedRect where the selection starts every time its size is updated, until the user moves it, params: selection");
 
 #[derive(Copy, Clone, PartialEq)]
 enum IfMousePressedWhere {
@@ -28,7 +32,11 @@ pub struct SelectedRect {
     rect: Rect,
     mouse: IfMousePressedWhere,
     show_overlay: bool,
-    fix_rect: Rect
+    fix_rect: Rect,
+    /// width / height of the selection, `None` when it is free
+    aspect: Option<f64>,
+    /// the selection used after a reset instead of the whole area, until the user moves it
+    pending_selection: Option<Rect>,
 }
 
 impl SelectedRect {
@@ -52,7 +60,9 @@ impl SelectedRect {
             rect,
             mouse: IfMousePressedWhere::NotInterested,
             show_overlay: false,
-            fix_rect: rect
+            fix_rect: rect,
+            aspect: None,
+            pending_selection: None,
         }
     }
 
@@ -100,6 +110,56 @@ impl SelectedRect {
         self.mouse = IfMousePressedWhere::NotInterested;
         self.show_overlay = false;
         self.fix_rect = rect_updated;
+        if let Some(selection) = self.pending_selection {
+            let selection = selection.intersect(rect_updated);
+            if selection.area() > 0. {
+                self.rect = selection;
+            }
+        }
+        self.keep_aspect();
+    }
+
+    /// The largest rect with the selected ratio that fits inside `rect`, with the same center.
+    fn fit_aspect(rect: Rect, aspect: f64) -> Rect {
+        let size = if rect.width() / rect.height() > aspect {
+            Size::new(rect.height() * aspect, rect.height())
+        } else {
+            Size::new(rect.width(), rect.width() / aspect)
+        };
+        Rect::from_center_size(rect.center(), size)
+    }
+
+    /// Changes the selection to follow the selected ratio: the side being dragged decides the
+    /// size, the opposite corner stays still.
+    fn keep_aspect(&mut self) {
+        let aspect = match self.aspect {
+            Some(aspect) if self.rect.height() > 0. => aspect,
+            _ => return,
+        };
+        match self.mouse {
+            IfMousePressedWhere::North | IfMousePressedWhere::South => {
+                self.rect.x1 = self.rect.x0 + self.rect.height() * aspect;
+            }
+            IfMousePressedWhere::NorthEst | IfMousePressedWhere::NorthWest => {
+                self.rect.y0 = self.rect.y1 - self.rect.width() / aspect;
+            }
+            IfMousePressedWhere::Est
+            | IfMousePressedWhere::SouthEst
+            | IfMousePressedWhere::SouthWest
+            | IfMousePressedWhere::West => {
+                self.rect.y1 = self.rect.y0 + self.rect.width() / aspect;
+            }
+            IfMousePressedWhere::Inside(_) | IfMousePressedWhere::NotInterested => {
+                if (self.rect.width() / self.rect.height() - aspect).abs() > 0.01 {
+                    self.rect = Self::fit_aspect(self.rect, aspect);
+                }
+            }
+        }
+        // a selection grown outside the area shrinks back, keeping its ratio
+        let inside = self.rect.intersect(self.fix_rect);
+        if inside != self.rect && inside.area() > 0. {
+            self.rect = Self::fit_aspect(inside, aspect);
+        }
     }
 }
 
@@ -116,8 +176,18 @@ impl Widget<Rect> for SelectedRect {
                 self.reset_rect(rect);
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(SET_CROP_SELECTION) => {
+                self.pending_selection = Some(*cmd.get_unchecked(SET_CROP_SELECTION));
+            }
+            Event::Command(cmd) if cmd.is(SET_CROP_ASPECT) => {
+                self.aspect = *cmd.get_unchecked(SET_CROP_ASPECT);
+                self.mouse = IfMousePressedWhere::NotInterested;
+                self.keep_aspect();
+                ctx.request_layout();
+            }
             Event::MouseDown(me) => {
                 ctx.set_active(true);
+                self.pending_selection = None;
                 self.mouse = self.where_mouse_is(me);
                 self.show_overlay = true;
             }
@@ -226,6 +296,9 @@ impl Widget<Rect> for SelectedRect {
         if self.rect.y1 > self.fix_rect.y1 {
             self.rect.y1 = self.fix_rect.y1 - BORDER_WIDTH;
         }
+        if !matches!(self.mouse, IfMousePressedWhere::NotInterested) {
+            self.keep_aspect();
+        }
         *data = self.rect;
     }
 
@@ -8,7 +8,7 @@ use flate2::Compression;
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use image::EncodableLayout;
-use crate::{BASE_PATH_FAVORITE_SHORTCUT, BASE_PATH_SCREENSHOT};
+use crate::{BASE_PATH_FAVORITE_SHORTCUT, BASE_PATH_SCREENSHOT, BASE_PATH_SETTINGS, BASE_PATH_STAMPS};
 
 pub const SHORTCUT_KEYS: Selector = Selector::new("ShortcutKeys-Command");
 
@@ -54,7 +54,6 @@ pub fn write_to_file<T>(file_path: &str, data: &T) -> Result<(), Box<dyn std::er
     where
         T: serde::Serialize,
 {
-    verify_exists_dir(BASE_PATH_FAVORITE_SHORTCUT);
     let serialized_data = serde_json::to_string(data)?;
     let binding = compress(serialized_data.as_bytes())?;
     let compress_serialized_data = binding.as_bytes();
@@ -99,6 +98,10 @@ pub fn verify_exists_dir(path: &str) {
                     eprintln!("Error during the creation of the screenshots directory, please create it manually with the name 'screenshots' in the src dir!");
                 } else if path.eq(BASE_PATH_FAVORITE_SHORTCUT) {
                     eprintln!("Error during the creation of the favorite shortcut directory, please create it manually with the name 'shortcut' in the src dir!");
+                } else if path.eq(BASE_PATH_STAMPS) {
+                    eprintln!("Error during the creation of the stamps directory, please create it manually with the name 'stamps' in the src dir!");
+                } else if path.eq(BASE_PATH_SETTINGS) {
+                    eprintln!("Error during the creation of the settings directory, please create it manually with the name 'settings' in the src dir!");
                 }
                 exit(1);
             }
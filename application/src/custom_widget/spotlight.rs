@@ -0,0 +1,106 @@
+use druid::{Data, Lens, Point, Rect};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// largest downscaling used to blur the screenshot, reached when the amount is 100
+const MAX_BLUR_FACTOR: f64 = 16.;
+
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SpotlightShape {
+    Rectangle,
+    Ellipse,
+}
+
+/// What happens to the screenshot outside the spotlight regions.
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SpotlightEffect {
+    Darken,
+    Blur,
+}
+
+/// How the spotlight is drawn, shared by all its regions but the shape, that is chosen for each
+/// new region.
+#[derive(Clone, Copy, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct SpotlightSettings {
+    pub(crate) shape: SpotlightShape,
+    pub(crate) effect: SpotlightEffect,
+    /// how much the screenshot is darkened or blurred, from 0 to 100
+    pub(crate) amount: f64,
+}
+
+impl Default for SpotlightSettings {
+    fn default() -> Self {
+        Self {
+            shape: SpotlightShape::Rectangle,
+            effect: SpotlightEffect::Darken,
+            amount: 60.,
+        }
+    }
+}
+
+/// A part of the screenshot left untouched by the spotlight.
+#[derive(Clone, Copy)]
+pub struct SpotlightRegion {
+    /// in pixels of the screenshot
+    pub(crate) rect: Rect,
+    pub(crate) shape: SpotlightShape,
+}
+
+impl SpotlightRegion {
+    pub fn contains(&self, point: Point) -> bool {
+        match self.shape {
+            SpotlightShape::Rectangle => self.rect.contains(point),
+            SpotlightShape::Ellipse => {
+                let center = self.rect.center();
+                let (rx, ry) = (self.rect.width() / 2., self.rect.height() / 2.);
+                if rx <= 0. || ry <= 0. {
+                    return false;
+                }
+                let (dx, dy) = ((point.x - center.x) / rx, (point.y - center.y) / ry);
+                dx * dx + dy * dy <= 1.
+            }
+        }
+    }
+}
+
+/// Darkens or blurs the image outside the regions, nothing happens without regions.
+pub fn apply_spotlight(img: &mut DynamicImage, regions: &[SpotlightRegion], settings: &SpotlightSettings) {
+    if regions.is_empty() || settings.amount <= 0. {
+        return;
+    }
+    let (width, height) = img.dimensions();
+    let amount = settings.amount.clamp(0., 100.) / 100.;
+
+    let effect: RgbaImage = match settings.effect {
+        SpotlightEffect::Darken => {
+            let mut dark = img.to_rgba8();
+            for px in dark.pixels_mut() {
+                for c in 0..3 {
+                    px[c] = (px[c] as f64 * (1. - amount)).round() as u8;
+                }
+            }
+            dark
+        }
+        SpotlightEffect::Blur => {
+            // the image is shrunk and enlarged again, much faster than a gaussian blur
+            let factor = 1. + (MAX_BLUR_FACTOR - 1.) * amount;
+            let small = img.resize_exact(
+                ((width as f64 / factor) as u32).max(1),
+                ((height as f64 / factor) as u32).max(1),
+                FilterType::Triangle,
+            );
+            small.resize_exact(width, height, FilterType::Triangle).to_rgba8()
+        }
+    };
+
+    let mut out = img.to_rgba8();
+    for (x, y, px) in out.enumerate_pixels_mut() {
+        let center = Point::new(x as f64 + 0.5, y as f64 + 0.5);
+        if !regions.iter().any(|region| region.contains(center)) {
+            let effect_px: &Rgba<u8> = effect.get_pixel(x, y);
+            *px = Rgba([effect_px[0], effect_px[1], effect_px[2], px[3]]);
+        }
+    }
+    *img = DynamicImage::ImageRgba8(out);
+}
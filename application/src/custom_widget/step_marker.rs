@@ -0,0 +1,61 @@
+use druid::{Color, Point};
+use image::{DynamicImage, Rgba};
+use imageproc::drawing::{draw_filled_circle_mut, draw_text_mut, text_size};
+use rusttype::Scale;
+use crate::custom_widget::text_style::{contrast_color, load_font};
+
+/// radius of a step marker, in pixels of the screenshot
+pub const MARKER_RADIUS: f64 = 18.;
+
+/// A numbered circle placed on the screenshot, its number is given by its position in the list.
+#[derive(Clone)]
+pub struct StepMarker {
+    pub(crate) center: Point,
+    pub(crate) color: Color,
+}
+
+impl StepMarker {
+    pub fn new(center: Point, color: Color) -> Self {
+        Self { center, color }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.center.distance(point) <= MARKER_RADIUS
+    }
+
+    /// The color of the number written inside the marker.
+    pub fn text_color(&self) -> Color {
+        let text_color = contrast_color(rgba(&self.color));
+        Color::rgba8(text_color[0], text_color[1], text_color[2], text_color[3])
+    }
+}
+
+fn rgba(color: &Color) -> Rgba<u8> {
+    let (r, g, b, a) = color.as_rgba8();
+    Rgba([r, g, b, a])
+}
+
+/// Draws the markers on the image, numbered from 1 following their order.
+pub fn draw_step_markers(img: &mut DynamicImage, markers: &[StepMarker]) {
+    let font = load_font();
+    let scale = Scale::uniform(MARKER_RADIUS as f32 * 1.2);
+
+    for (i, marker) in markers.iter().enumerate() {
+        let center = (marker.center.x as i32, marker.center.y as i32);
+        draw_filled_circle_mut(img, center, MARKER_RADIUS as i32, rgba(&marker.color));
+
+        let number = (i + 1).to_string();
+        let (width, _) = text_size(scale, &font, number.as_str());
+        let v_metrics = font.v_metrics(scale);
+        let height = (v_metrics.ascent - v_metrics.descent) as i32;
+        draw_text_mut(
+            img,
+            rgba(&marker.text_color()),
+            center.0 - width / 2,
+            center.1 - height / 2,
+            scale,
+            &font,
+            number.as_str(),
+        );
+    }
+}
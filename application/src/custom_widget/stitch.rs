@@ -0,0 +1,115 @@
+use druid::{Data, FileInfo, Lens, Selector};
+use image::imageops::overlay;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+pub const SHOW_STITCH: Selector = Selector::new("Open the window to combine several images into one");
+pub const SAVE_STITCH: Selector<FileInfo> = Selector::new("Save the combined image in the file chosen by the user");
+
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum StitchLayout {
+    /// all the images in a row
+    Horizontal,
+    /// all the images in a column
+    Vertical,
+    /// rows of a fixed number of images
+    Grid,
+}
+
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum StitchBackground {
+    White,
+    Black,
+    Transparent,
+}
+
+impl StitchBackground {
+    fn color(&self) -> Rgba<u8> {
+        match self {
+            StitchBackground::White => Rgba([255, 255, 255, 255]),
+            StitchBackground::Black => Rgba([0, 0, 0, 255]),
+            StitchBackground::Transparent => Rgba([0, 0, 0, 0]),
+        }
+    }
+}
+
+/// How the images are arranged in the combined one.
+#[derive(Clone, Copy, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct StitchSettings {
+    pub(crate) layout: StitchLayout,
+    /// images in every row of the grid
+    pub(crate) columns: f64,
+    /// distance between the images and from the borders, in pixels
+    pub(crate) spacing: f64,
+    pub(crate) background: StitchBackground,
+}
+
+impl Default for StitchSettings {
+    fn default() -> Self {
+        Self {
+            layout: StitchLayout::Horizontal,
+            columns: 2.,
+            spacing: 16.,
+            background: StitchBackground::White,
+        }
+    }
+}
+
+impl StitchSettings {
+    /// Checks if the combined image has transparent pixels, that only some formats can keep.
+    pub fn needs_alpha(&self) -> bool {
+        self.background == StitchBackground::Transparent
+    }
+}
+
+/// Puts the images one after the other in a single image: every image is centered in its cell,
+/// that is as wide as the widest image of its column and as tall as the tallest one of its row.
+///
+/// Returns `None` if there are no images.
+pub fn stitch(images: &[DynamicImage], settings: &StitchSettings) -> Option<DynamicImage> {
+    if images.is_empty() {
+        return None;
+    }
+    let columns = match settings.layout {
+        StitchLayout::Horizontal => images.len(),
+        StitchLayout::Vertical => 1,
+        StitchLayout::Grid => (settings.columns.round().max(1.) as usize).min(images.len()),
+    };
+    let rows = (images.len() + columns - 1) / columns;
+    let spacing = settings.spacing.round().max(0.) as u32;
+
+    let mut widths = vec![0; columns];
+    let mut heights = vec![0; rows];
+    for (i, img) in images.iter().enumerate() {
+        widths[i % columns] = widths[i % columns].max(img.width());
+        heights[i / columns] = heights[i / columns].max(img.height());
+    }
+    // where every column and row starts
+    let xs: Vec<u32> = widths
+        .iter()
+        .scan(spacing, |x, width| {
+            let start = *x;
+            *x += width + spacing;
+            Some(start)
+        })
+        .collect();
+    let ys: Vec<u32> = heights
+        .iter()
+        .scan(spacing, |y, height| {
+            let start = *y;
+            *y += height + spacing;
+            Some(start)
+        })
+        .collect();
+
+    let width = widths.iter().sum::<u32>() + spacing * (columns as u32 + 1);
+    let height = heights.iter().sum::<u32>() + spacing * (rows as u32 + 1);
+    let mut out = RgbaImage::from_pixel(width, height, settings.background.color());
+    for (i, img) in images.iter().enumerate() {
+        let (column, row) = (i % columns, i / columns);
+        let x = xs[column] + (widths[column] - img.width()) / 2;
+        let y = ys[row] + (heights[row] - img.height()) / 2;
+        overlay(&mut out, &img.to_rgba8(), x as i64, y as i64);
+    }
+    Some(DynamicImage::ImageRgba8(out))
+}
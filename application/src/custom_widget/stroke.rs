@@ -0,0 +1,68 @@
+use druid::{Color, Point, Rect};
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_circle_mut, draw_filled_rect_mut};
+
+/// width of a highlighter stroke, in pixels of the screenshot
+pub const STROKE_WIDTH: f64 = 16.;
+
+/// The shape drawn by the highlighter while the mouse is dragged.
+#[derive(Clone, Copy, PartialEq)]
+pub enum StrokeShape {
+    /// the stroke follows the mouse
+    Freehand,
+    /// a filled rectangle between the point where the drag started and the current one
+    Rectangle,
+}
+
+/// The rect covered by the stroke, in pixels of the screenshot.
+pub fn stroke_bounds(points: &[Point], shape: StrokeShape) -> Rect {
+    let mut bounds = Rect::from_points(points[0], points[0]);
+    for point in points.iter() {
+        bounds = bounds.union_pt(*point);
+    }
+    match shape {
+        StrokeShape::Freehand => bounds.inflate(STROKE_WIDTH / 2., STROKE_WIDTH / 2.),
+        StrokeShape::Rectangle => Rect::from_points(points[0], *points.last().unwrap()),
+    }
+    .expand()
+}
+
+/// Renders the stroke into an image as large as its bounds, fully opaque where it passes.
+///
+/// Returns the image and where it lies in pixels of the screenshot, `None` for an empty stroke.
+pub fn stroke_to_image(points: &[Point], shape: StrokeShape, color: Color) -> Option<(DynamicImage, Rect)> {
+    if points.is_empty() {
+        return None;
+    }
+    let bounds = stroke_bounds(points, shape);
+    if bounds.width() < 1. || bounds.height() < 1. {
+        return None;
+    }
+
+    let (r, g, b, _) = color.as_rgba8();
+    let stroke_color = Rgba([r, g, b, u8::MAX]);
+    let mut img = RgbaImage::new(bounds.width() as u32, bounds.height() as u32);
+    match shape {
+        StrokeShape::Freehand => {
+            let radius = (STROKE_WIDTH / 2.) as i32;
+            let mut previous = points[0];
+            for point in points.iter() {
+                // the segment is covered by circles one pixel apart, so that fast movements leave no holes
+                let steps = previous.distance(*point).ceil().max(1.) as usize;
+                for step in 0..=steps {
+                    let center = previous.lerp(*point, step as f64 / steps as f64) - bounds.origin().to_vec2();
+                    draw_filled_circle_mut(&mut img, (center.x as i32, center.y as i32), radius, stroke_color);
+                }
+                previous = *point;
+            }
+        }
+        StrokeShape::Rectangle => {
+            draw_filled_rect_mut(
+                &mut img,
+                imageproc::rect::Rect::at(0, 0).of_size(img.width(), img.height()),
+                stroke_color,
+            );
+        }
+    }
+    Some((DynamicImage::ImageRgba8(img), bounds))
+}
@@ -1,6 +1,7 @@
 use std::sync::{Arc};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use device_query::{DeviceQuery, DeviceState, Keycode};
 use druid::debug_state::DebugState;
 use druid::widget::prelude::*;
 use druid::widget::{Click, ControllerHost, Label, LabelText};
@@ -8,10 +9,13 @@ use druid::{commands as sys_cmd, theme, Affine, Data, Insets, LinearGradient, Un
 use image::{DynamicImage, ImageFormat};
 use screenshots::{Screen};
 use tracing::{instrument, trace};
-use crate::BASE_PATH_SCREENSHOT;
-use crate::custom_widget::{UPDATE_BACK_IMG, UPDATE_SCREENSHOT, verify_exists_dir};
+use crate::{BASE_PATH_SCREENSHOT, PATH_ENCODE, PATH_WATERMARK};
+use crate::custom_widget::{read_from_file, save_image, EncodeSettings, SHOW_ALERT, UPDATE_BACK_IMG, UPDATE_SCREENSHOT, verify_exists_dir};
+use crate::custom_widget::scroll_capture::{FrameResult, ScrollStitcher, SCROLL_FRAME_INTERVAL, SCROLL_IDLE_FRAMES, SCROLL_KEY_POLL_INTERVAL, SCROLL_MAX_HEIGHT, SCROLL_START_FRAMES};
+use crate::custom_widget::watermark::{apply_watermark, CaptureInfo, WatermarkSettings, CAPTURE_INFO};
 
 pub const SAVE_SCREENSHOT: Selector<(Rect,WindowId,WidgetId,WidgetId,Box<str>,Box<str>,ImageFormat,u64,u8)> = Selector::new("Save the screenshot image, last param: where to save");
+pub const SCROLL_CAPTURE: Selector<(Rect,WindowId,WidgetId,WidgetId,Box<str>,Box<str>,ImageFormat,u64,u8)> = Selector::new("Capture the area while the page under it is scrolled and save the tall image, params: the ones of SAVE_SCREENSHOT");
 
 // the minimum padding added to a button.
 // NOTE: these values are chosen to match the existing look of TextBox; these
@@ -24,6 +28,8 @@ pub struct TakeScreenshotButton<T> {
     label_size: Size,
     color: Option<Color>,
     taking_screenshot: Option<(Rect,WindowId,WidgetId,WidgetId,Box<str>,Box<str>,ImageFormat,Duration,u8)>,
+    /// the screenshot being taken is a scrolling capture
+    scrolling: bool,
 }
 
 #[allow(dead_code)]
@@ -71,6 +77,7 @@ impl<T: Data> TakeScreenshotButton<T> {
             label_size: Size::ZERO,
             color: None,
             taking_screenshot: None,
+            scrolling: false,
         }
     }
 
@@ -113,6 +120,7 @@ impl<T: Data> TakeScreenshotButton<T> {
             label_size:self.label_size,
             color:Some(color),
             taking_screenshot: self.taking_screenshot,
+            scrolling: self.scrolling,
         }
     }
 }
@@ -133,7 +141,41 @@ impl<T: Data> Widget<T> for TakeScreenshotButton<T> {
                 timeout_thread.join().unwrap();
             }
 
-            let new_img = Arc::new(save_screenshot(&rect,path.clone(),file_name.clone(),*file_format, *monitor as usize));
+            if self.scrolling {
+                // the frames are captured in another thread, so that the user can scroll the page
+                // while the application keeps running
+                let (rect,main_window_id,custom_zstack_id,screenshot_id,path,file_name,file_format, _, monitor) = self.taking_screenshot.take().unwrap();
+                self.scrolling = false;
+                let handle = ctx.get_external_handle();
+                let window_id = ctx.window_id();
+                thread::spawn(move || {
+                    let capture_info = CaptureInfo::now(Some(monitor as usize));
+                    let (img, message) = capture_scrolling(&rect, monitor as usize);
+                    let message = match save_capture(&img, path, file_name, file_format, &capture_info) {
+                        Ok(()) => message,
+                        Err(e) => format!("The scrolling capture has not been saved: {}", e),
+                    };
+                    let new_img = Arc::new(img);
+
+                    handle.submit_command(sys_cmd::SHOW_WINDOW, (), main_window_id)
+                        .expect("Error sending the event to the window");
+                    handle.submit_command(UPDATE_SCREENSHOT, new_img.clone(), screenshot_id)
+                        .expect("Error sending the event to the screenshot widget");
+                    handle.submit_command(UPDATE_BACK_IMG, new_img, custom_zstack_id)
+                        .expect("Error sending the event to the screenshot widget");
+                    handle.submit_command(CAPTURE_INFO, capture_info, custom_zstack_id)
+                        .expect("Error sending the event to the screenshot widget");
+                    handle.submit_command(SHOW_ALERT, message, main_window_id)
+                        .expect("Error sending the event to the window");
+                    handle.submit_command(sys_cmd::CLOSE_WINDOW, (), window_id)
+                        .expect("Error sending the event to the window");
+                });
+                return;
+            }
+
+            let capture_info = CaptureInfo::now(Some(*monitor as usize));
+            let (new_img, saved) = save_screenshot(&rect,path.clone(),file_name.clone(),*file_format, *monitor as usize, &capture_info);
+            let new_img = Arc::new(new_img);
             let main_id = main_window_id;
 
             ctx.get_external_handle()
@@ -145,15 +187,27 @@ impl<T: Data> Widget<T> for TakeScreenshotButton<T> {
             ctx.get_external_handle()
                 .submit_command(UPDATE_BACK_IMG,new_img,*custom_zstack_id)
                 .expect("Error sending the event to the screenshot widget");
+            ctx.get_external_handle()
+                .submit_command(CAPTURE_INFO,capture_info,*custom_zstack_id)
+                .expect("Error sending the event to the screenshot widget");
+            if let Err(e) = saved {
+                ctx.get_external_handle()
+                    .submit_command(SHOW_ALERT, format!("The screenshot has not been saved: {}", e), *main_id)
+                    .expect("Error sending the event to the window");
+            }
             self.taking_screenshot = None;
             ctx.window().close();
         }
 
         match event {
             Event::Command(cmd) => {
-                if cmd.is(SAVE_SCREENSHOT) {
+                if cmd.is(SAVE_SCREENSHOT) || cmd.is(SCROLL_CAPTURE) {
                     ctx.window().hide();
-                    let (rect,main_window_id,custom_zstack_id,screenshot_id,path,file_name,file_format, delay, monitor) = cmd.get_unchecked(SAVE_SCREENSHOT);
+                    self.scrolling = cmd.is(SCROLL_CAPTURE);
+                    let (rect,main_window_id,custom_zstack_id,screenshot_id,path,file_name,file_format, delay, monitor) = cmd
+                        .get(SAVE_SCREENSHOT)
+                        .or(cmd.get(SCROLL_CAPTURE))
+                        .unwrap();
                     let timeout = Duration::from_secs(*delay);
                     self.taking_screenshot = Some((*rect,*main_window_id,*custom_zstack_id,*screenshot_id,path.clone(),file_name.clone(),*file_format, timeout, *monitor));
                     ctx.request_layout();
@@ -281,20 +335,87 @@ impl<T: Data> Widget<T> for TakeScreenshotButton<T> {
 }
 
 
-fn save_screenshot(rect: &Rect, base_path: Box<str>, file_name: Box<str>, format: ImageFormat, monitor: usize) -> DynamicImage{
+/// Captures the area of the monitor and saves it with the watermark, the returned image is the one
+/// to edit and has no watermark.
+///
+/// Returns the image and whether it has been saved.
+fn save_screenshot(rect: &Rect, base_path: Box<str>, file_name: Box<str>, format: ImageFormat, monitor: usize, capture_info: &CaptureInfo) -> (DynamicImage, Result<(), String>){
+    let dyn_img = capture_area(rect, monitor);
+    let saved = save_capture(&dyn_img, base_path, file_name, format, capture_info);
+    (dyn_img, saved)
+}
+
+/// Captures the area of the monitor.
+fn capture_area(rect: &Rect, monitor: usize) -> DynamicImage {
     let screens = Screen::all().unwrap();
     let screen = screens.get(monitor).expect("Can't find the selected monitor!");
     let image = screen.capture_area(rect.x0 as i32, rect.y0 as i32, rect.width() as u32, rect.height() as u32).unwrap();
 
-    let dyn_img = DynamicImage::from(
-        image.clone()
-    );
+    DynamicImage::from(image)
+}
+
+/// Captures the area again and again while the page under it is scrolled down, until the page
+/// stops moving, the image gets too tall or Esc is pressed.
+///
+/// The page is scrolled by the user: the frames that don't overlap the previous one, because the
+/// page moved too far or up, are skipped. The user has longer to start scrolling, the frames
+/// without movement are counted once the page has moved. Returns the image and the message for
+/// the user.
+fn capture_scrolling(rect: &Rect, monitor: usize) -> (DynamicImage, String) {
+    let device = DeviceState::new();
+    let mut stitcher = ScrollStitcher::new(capture_area(rect, monitor).into_rgba8());
+    let (mut started, mut idle, mut stopped) = (false, 0, false);
+    while stitcher.height() < SCROLL_MAX_HEIGHT {
+        if idle >= if started { SCROLL_IDLE_FRAMES } else { SCROLL_START_FRAMES } {
+            break;
+        }
+        if stop_pressed(&device, SCROLL_FRAME_INTERVAL) {
+            stopped = true;
+            break;
+        }
+        match stitcher.push(capture_area(rect, monitor).into_rgba8()) {
+            FrameResult::Appended(_) => {
+                started = true;
+                idle = 0;
+            }
+            FrameResult::Unchanged | FrameResult::NoOverlap => idle += 1,
+        }
+    }
+
+    let message = if stitcher.trailing_skipped > 0 {
+        "The page moved too far between two frames, the capture stopped there: scroll slower!".to_string()
+    } else if stitcher.height() >= SCROLL_MAX_HEIGHT {
+        format!("The capture stopped at the maximum height of {} pixels!", SCROLL_MAX_HEIGHT)
+    } else if !started && !stopped {
+        "The page has not been scrolled, only the visible area has been saved on the disk!".to_string()
+    } else {
+        "The scrolling capture has been saved on the disk!".to_string()
+    };
+    (stitcher.into_image(), message)
+}
 
+/// Waits for the next frame of the scrolling capture, returns whether Esc has been pressed
+/// meanwhile to stop it.
+fn stop_pressed(device: &DeviceState, wait: Duration) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < wait {
+        if device.get_keys().contains(&Keycode::Escape) {
+            return true;
+        }
+        thread::sleep(SCROLL_KEY_POLL_INTERVAL);
+    }
+    false
+}
+
+/// Saves the captured image with the watermark, nothing is saved if the watermark can't be applied.
+fn save_capture(dyn_img: &DynamicImage, base_path: Box<str>, file_name: Box<str>, format: ImageFormat, capture_info: &CaptureInfo) -> Result<(), String> {
     // it verify if exists the dir before saving the image
     verify_exists_dir(BASE_PATH_SCREENSHOT);
 
     let path = format!("{}{}.{}", base_path, file_name, format.extensions_str().first().unwrap());
-    dyn_img.save_with_format(path.clone(), format).unwrap();
-
-    dyn_img
+    let watermark = read_from_file::<WatermarkSettings>(PATH_WATERMARK).unwrap_or_default();
+    let mut saved_img = dyn_img.clone();
+    apply_watermark(&mut saved_img, &watermark, capture_info)?;
+    let encode = read_from_file::<EncodeSettings>(PATH_ENCODE).unwrap_or_default();
+    save_image(&saved_img, &path, format, &encode)
 }
\ No newline at end of file
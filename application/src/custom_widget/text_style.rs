@@ -0,0 +1,133 @@
+use druid::{Color, Data, Lens};
+use image::imageops::overlay;
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use rusttype::{Font, Scale};
+use serde::{Deserialize, Serialize};
+
+/// padding in pixels around the text when the background box or the outline are enabled
+const TEXT_PADDING: u32 = 6;
+/// horizontal shift of the top of a line, relative to the line height, used by the italic style
+const ITALIC_SHEAR: f32 = 0.2;
+
+/// How the text annotation is rendered on the screenshot.
+#[derive(Clone, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct TextStyle {
+    pub(crate) size: f64,
+    pub(crate) bold: bool,
+    pub(crate) italic: bool,
+    pub(crate) background: bool,
+    pub(crate) outline: bool,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 30.,
+            bold: false,
+            italic: false,
+            background: false,
+            outline: false,
+        }
+    }
+}
+
+/// Loads the font used to render every text drawn on the screenshot.
+pub(crate) fn load_font() -> Font<'static> {
+    let font_data: &'static [u8] = include_bytes!("../images/icons/DejaVuSans.ttf");
+    Font::try_from_bytes(font_data).unwrap()
+}
+
+/// Returns black or white, depending on which one is more readable together with `color`.
+pub(crate) fn contrast_color(color: Rgba<u8>) -> Rgba<u8> {
+    let luminance = 0.299 * color[0] as f64 + 0.587 * color[1] as f64 + 0.114 * color[2] as f64;
+    if luminance > 128. {
+        Rgba([0, 0, 0, u8::MAX])
+    } else {
+        Rgba([u8::MAX, u8::MAX, u8::MAX, u8::MAX])
+    }
+}
+
+/// Moves every row of the image to the right, the top of each line more than the bottom.
+fn shear(img: &RgbaImage, line_height: u32, padding: u32) -> RgbaImage {
+    let mut sheared = RgbaImage::new(img.width(), img.height());
+    for (x, y, px) in img.enumerate_pixels() {
+        let row_in_line = y.saturating_sub(padding) % line_height;
+        let shift = ((line_height - 1 - row_in_line) as f32 * ITALIC_SHEAR) as u32;
+        if x + shift < sheared.width() {
+            sheared.put_pixel(x + shift, y, *px);
+        }
+    }
+    sheared
+}
+
+/// Renders a (multi-line) text into an image as large as the text itself.
+pub fn text_to_image(text: &str, color: Option<Color>, style: &TextStyle) -> DynamicImage {
+    let font = load_font();
+
+    let scale = Scale::uniform(style.size as f32);
+    let v_metrics = font.v_metrics(scale);
+    let line_height = (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil() as u32;
+    let lines: Vec<&str> = text.lines().collect();
+
+    // the bold style is obtained drawing the text more times, moved by one pixel each time
+    let bold_offset = if style.bold {
+        (style.size / 15.).ceil().max(1.) as u32
+    } else {
+        0
+    };
+    let outline_width = (style.size / 15.).ceil().max(1.) as i32;
+    let italic_offset = if style.italic {
+        (line_height as f32 * ITALIC_SHEAR).ceil() as u32
+    } else {
+        0
+    };
+    let padding = if style.background || style.outline {
+        TEXT_PADDING
+    } else {
+        0
+    };
+
+    let text_width = lines
+        .iter()
+        .map(|line| text_size(scale, &font, line).0.max(0) as u32)
+        .max()
+        .unwrap_or(0);
+    let width = text_width.max(1) + bold_offset + italic_offset + 2 * padding;
+    let height = line_height * lines.len().max(1) as u32 + 2 * padding;
+
+    let default_color = Color::rgba8(255, 255, 255, 255);
+    let colors = color.unwrap_or(default_color).as_rgba8();
+    let text_color = Rgba([colors.0, colors.1, colors.2, colors.3]);
+    let contrast = contrast_color(text_color);
+
+    let mut text_layer = RgbaImage::new(width, height);
+    for (i, line) in lines.iter().enumerate() {
+        let x = padding as i32;
+        let y = (padding + i as u32 * line_height) as i32;
+        if style.outline {
+            for dx in -outline_width..=outline_width + bold_offset as i32 {
+                for dy in -outline_width..=outline_width {
+                    draw_text_mut(&mut text_layer, contrast, x + dx, y + dy, scale, &font, line);
+                }
+            }
+        }
+        for dx in 0..=bold_offset as i32 {
+            draw_text_mut(&mut text_layer, text_color, x + dx, y, scale, &font, line);
+        }
+    }
+
+    if style.italic {
+        text_layer = shear(&text_layer, line_height, padding);
+    }
+
+    let image = if style.background {
+        let mut background = RgbaImage::from_pixel(width, height, contrast);
+        overlay(&mut background, &text_layer, 0, 0);
+        background
+    } else {
+        text_layer
+    };
+
+    DynamicImage::ImageRgba8(image)
+}
@@ -0,0 +1,170 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use druid::{Color, Data, Lens, Selector};
+use image::imageops::{overlay, FilterType};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use crate::custom_widget::text_style::text_to_image;
+use crate::custom_widget::TextStyle;
+
+pub const SET_WATERMARK: Selector<WatermarkSettings> =
+    Selector::new("Tell the ZStack the watermark applied on every save, params: watermark settings");
+pub const CAPTURE_INFO: Selector<CaptureInfo> =
+    Selector::new("Tell the ZStack when and where the screenshot has been taken, params: capture info");
+
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WatermarkKind {
+    /// a text, where the tokens are replaced by the information about the capture
+    Text,
+    /// an image read from a file
+    Image,
+}
+
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+/// The watermark applied to every saved screenshot, it is saved together with the other settings
+/// of the application.
+#[derive(Clone, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct WatermarkSettings {
+    pub(crate) enabled: bool,
+    pub(crate) kind: WatermarkKind,
+    /// the text of the watermark, `{date}`, `{time}`, `{user}` and `{monitor}` are replaced when
+    /// it is applied
+    pub(crate) text: String,
+    /// the path of the image of the watermark
+    pub(crate) image_path: String,
+    pub(crate) position: WatermarkPosition,
+    /// distance from the borders of the screenshot, in pixels
+    pub(crate) margin: f64,
+    /// from 0 to 100
+    pub(crate) opacity: f64,
+    /// the size of the font for a text, the width in percent of the screenshot for an image
+    pub(crate) size: f64,
+}
+
+impl Default for WatermarkSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kind: WatermarkKind::Text,
+            text: "Captured {date} {time} UTC by {user}".to_string(),
+            image_path: "".to_string(),
+            position: WatermarkPosition::BottomRight,
+            margin: 10.,
+            opacity: 80.,
+            size: 20.,
+        }
+    }
+}
+
+/// When and where a screenshot has been taken.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CaptureInfo {
+    /// seconds since the Unix epoch
+    pub(crate) captured_at: u64,
+    /// index of the monitor, `None` if the image has not been captured from a monitor
+    pub(crate) monitor: Option<usize>,
+}
+
+impl CaptureInfo {
+    /// A capture taken now.
+    pub fn now(monitor: Option<usize>) -> Self {
+        Self {
+            captured_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            monitor,
+        }
+    }
+}
+
+/// Converts seconds since the Unix epoch into (year, month, day, hours, minutes, seconds) in UTC.
+fn utc_date_time(secs: u64) -> (i64, u64, u64, u64, u64, u64) {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // civil date from the days since 1970-01-01, counting eras of 400 years starting in March
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u64;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u64;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, secs_of_day / 3600, secs_of_day % 3600 / 60, secs_of_day % 60)
+}
+
+/// Replaces the tokens of the text with the information about the capture.
+pub fn expand_tokens(text: &str, info: &CaptureInfo) -> String {
+    let (year, month, day, hours, minutes, seconds) = utc_date_time(info.captured_at);
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    let monitor = match info.monitor {
+        Some(monitor) => (monitor + 1).to_string(),
+        None => "-".to_string(),
+    };
+    text.replace("{date}", &format!("{:04}-{:02}-{:02}", year, month, day))
+        .replace("{time}", &format!("{:02}:{:02}:{:02}", hours, minutes, seconds))
+        .replace("{user}", &user)
+        .replace("{monitor}", &monitor)
+}
+
+/// Draws the watermark on the image, if it is enabled.
+///
+/// Returns an error if the image of the watermark can't be read, the screenshot is left untouched.
+pub fn apply_watermark(img: &mut DynamicImage, settings: &WatermarkSettings, info: &CaptureInfo) -> Result<(), String> {
+    if !settings.enabled {
+        return Ok(());
+    }
+    let mark = match settings.kind {
+        WatermarkKind::Text => {
+            let text = expand_tokens(&settings.text, info);
+            if text.trim().is_empty() {
+                return Ok(());
+            }
+            // the outline keeps the text readable on any background
+            let style = TextStyle {
+                size: settings.size.max(1.),
+                outline: true,
+                ..TextStyle::default()
+            };
+            text_to_image(&text, Some(Color::WHITE), &style)
+        }
+        WatermarkKind::Image => {
+            let mark = image::open(&settings.image_path)
+                .map_err(|_| format!("The watermark image {} can't be read!", settings.image_path))?;
+            let width = (img.width() as f64 * settings.size / 100.).round().max(1.) as u32;
+            let height = (mark.height() as f64 * width as f64 / mark.width().max(1) as f64).round().max(1.) as u32;
+            mark.resize_exact(width, height, FilterType::CatmullRom)
+        }
+    };
+
+    let mut mark = mark.into_rgba8();
+    let opacity = (settings.opacity / 100.).clamp(0., 1.);
+    mark.pixels_mut()
+        .for_each(|px| px[3] = (px[3] as f64 * opacity).round() as u8);
+
+    let margin = settings.margin.max(0.) as i64;
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let (mark_width, mark_height) = (mark.width() as i64, mark.height() as i64);
+    let (x, y) = match settings.position {
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::TopRight => (width - mark_width - margin, margin),
+        WatermarkPosition::BottomLeft => (margin, height - mark_height - margin),
+        WatermarkPosition::BottomRight => (width - mark_width - margin, height - mark_height - margin),
+        WatermarkPosition::Center => ((width - mark_width) / 2, (height - mark_height) / 2),
+    };
+    overlay(img, &mark, x, y);
+    Ok(())
+}
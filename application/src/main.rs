@@ -1,21 +1,25 @@
 mod custom_widget;
 
-use crate::custom_widget::{read_from_file, write_to_file, Alert, ColoredButton, CustomSlider, CustomZStack, OverImages, ScreenshotImage, SelectedRect, ShortcutKeys, StateShortcutKeys, TakeScreenshotButton, CREATE_ZSTACK, SAVE_OVER_IMG, SAVE_SCREENSHOT, SHORTCUT_KEYS, SHOW_OVER_IMG, UPDATE_BACK_IMG, UPDATE_COLOR, UPDATE_RECT_SIZE, UPDATE_SCREENSHOT_CROP, UPDATE_SCREENSHOT_CROP_CLOSE, verify_exists_dir};
+use crate::custom_widget::{read_from_file, SCROLL_CAPTURE, CompareController, CompareMode, CompareSettings, CompareSide, COMPARE_AFTER, COMPARE_BEFORE, COMPARE_CHANGED, LOAD_COMPARE_IMAGE, SET_COMPARE, SHOW_COMPARE, SHOW_COMPARE_ALERT, stitch, StitchBackground, StitchLayout, StitchSettings, SAVE_STITCH, SHOW_STITCH, AUTO_TRIM, Adjustments, SET_ADJUSTMENTS, START_CROP, CANCEL_CROP, RESET_CROP, SET_CROP_ASPECT, Orientation, ORIENT_SCREENSHOT, ANNOTATION_SELECTED, SET_SPOTLIGHT, SET_CALLOUT_CONNECTOR, MeasureShape, SpotlightEffect, SpotlightSettings, SpotlightShape, BlendMode, BLEND_MODE_CHANGED, SET_BLEND_MODE, AnnotationStyle, ANNOTATION_STYLE_CHANGED, SET_ANNOTATION_STYLE, FLIP_OVER_IMG, WatermarkKind, WatermarkPosition, WatermarkSettings, SET_WATERMARK, ExportScale, ResampleFilter, ScaleMode, SET_EXPORT_SCALE, Beautify, BeautifyBackground, SET_BEAUTIFY, EncodeSettings, ENCODE_QUALITY_RANGE, SET_ENCODE, SAVE_FORMATS, is_save_format, keeps_alpha, save_image, SET_ZOOM, ZOOM_CHANGED, PAN_KEY, StrokeShape, Project, ProjectHeader, LOAD_PROJECT, OPEN_PROJECT, PROJECT_EXTENSION, SAVE_PROJECT, WRITE_PROJECT, ColorPickerState, HexColor, HueBar, SaturationValueSquare, COLOR_PICKED, START_EYEDROPPER, write_to_file, Alert, ColoredButton, CustomSlider, CustomZStack, OverImages, ScreenshotImage, SelectedRect, ShortcutKeys, StateShortcutKeys, TakeScreenshotButton, CREATE_ZSTACK, SAVE_OVER_IMG, SAVE_SCREENSHOT, SHORTCUT_KEYS, SHOW_ALERT, SHOW_OVER_IMG, TextStyle, UPDATE_BACK_IMG, UPDATE_COLOR, UPDATE_OVER_TEXT, UPDATE_RECT_SIZE, UPDATE_SCREENSHOT_CROP, UPDATE_SCREENSHOT_CROP_CLOSE, verify_exists_dir};
 use druid::commands::SHOW_ABOUT;
+use druid::im::Vector;
+use druid::text::ParseFormatter;
 use druid::piet::ImageFormat;
 use druid::widget::{
-    Align, Button, Click, Container, ControllerHost, CrossAxisAlignment, Either, FillStrat, Flex,
-    IdentityWrapper, Label, LensWrap, LineBreaking, MainAxisAlignment, Scroll, Stepper, TextBox,
+    Align, Axis, Button, Checkbox, Click, Container, Controller, ControllerHost, CrossAxisAlignment, Either, FillStrat, Flex,
+    IdentityWrapper, Image, Label, LensWrap, LineBreaking, MainAxisAlignment, Painter, Scroll, Stepper, TextBox,
     ViewSwitcher, ZStack,
 };
 use druid::Target::{Auto, Window};
 use druid::{
     commands as sys_cmd, commands, AppDelegate, AppLauncher, Code, Color, Command, Data,
-    DelegateCtx, Env, Event, EventCtx, FileDialogOptions, FontDescriptor, FontFamily, Handled,
-    ImageBuf, Lens, LocalizedString, Menu, MenuItem, Point, Rect, Screen, Size, Target,
-    TextAlignment, UnitPoint, Vec2, Widget, WidgetExt, WidgetId, WindowDesc, WindowId, WindowState,
+    DelegateCtx, Env, Event, EventCtx, FileDialogOptions, FileSpec, FontDescriptor, FontFamily, Handled,
+    ImageBuf, KbKey, Lens, LensExt, LifeCycle, LifeCycleCtx, LocalizedString, Menu, MenuItem, Point, Rect,
+    RenderContext, Screen, Size, Target, TextAlignment, UnitPoint, UpdateCtx, Vec2, Widget, WidgetExt, WidgetId,
+    WindowDesc, WindowId, WindowState,
 };
 use image::io::Reader;
+use image::DynamicImage;
 use random_string::generate;
 use std::collections::HashSet;
 use std::path::Path;
@@ -28,6 +32,7 @@ const STARTING_IMG_PATH: &'static str = "./src/images/starting_img.png";
 lazy_static::lazy_static! {
     static ref SCREENSHOT_WIDGET_ID: WidgetId = WidgetId::next();
     static ref ZSTACK_ID: WidgetId = WidgetId::next();
+    static ref COMPARE_WIDGET_ID: WidgetId = WidgetId::next();
 }
 
 const WINDOW_TITLE: LocalizedString<AppState> = LocalizedString::new("Screen Grabbing Application");
@@ -39,7 +44,20 @@ const Y1: f64 = 500.;
 const BASE_PATH: &str = "./src/";
 const BASE_PATH_SCREENSHOT: &str = "./src/screenshots/";
 const BASE_PATH_FAVORITE_SHORTCUT: &str = "./src/shortcut/";
+const BASE_PATH_STAMPS: &str = "./src/stamps/";
+/// size of the stamps previews in the stamp picker
+const STAMP_THUMBNAIL_SIZE: u32 = 40;
+/// size of the previews of the images that can be combined
+const STITCH_THUMBNAIL_SIZE: u32 = 90;
+/// images listed in the window to combine them, the most recent ones
+const STITCH_MAX_CANDIDATES: usize = 40;
+const STITCH_CANDIDATES_PER_ROW: usize = 5;
 const PATH_FAVORITE_SHORTCUT: &str = "./src/shortcut/shortcut_settings.json";
+const BASE_PATH_SETTINGS: &str = "./src/settings/";
+const PATH_WATERMARK: &str = "./src/settings/watermark_settings.json";
+const PATH_EXPORT_SCALE: &str = "./src/settings/export_scale_settings.json";
+const PATH_BEAUTIFY: &str = "./src/settings/beautify_settings.json";
+const PATH_ENCODE: &str = "./src/settings/encode_settings.json";
 
 #[derive(Clone, PartialEq)]
 enum ImageModified {
@@ -72,15 +90,55 @@ struct AppState {
     color: Option<Color>,
     #[data(ignore)]
     colors_window_opened: Option<WindowId>,
+    color_picker: ColorPickerState,
     #[data(ignore)]
     base_path: String,
     alert: Alert,
+    /// the alert shown in the compare window
+    compare_alert: Alert,
+    /// the alert shown in the combine window
+    stitch_alert: Alert,
     shortcut_keys: ShortcutKeys,
     #[data(ignore)]
     text_field_zstack: bool,
     text_field: String,
+    text_style: TextStyle,
+    text_editing: bool,
+    stamps_opened: bool,
+    blend_mode: BlendMode,
+    annotation_style: AnnotationStyle,
+    spotlight: SpotlightSettings,
+    spotlight_editing: bool,
+    callout_editing: bool,
+    callout_connector: bool,
+    /// layout points for every pixel of the screenshot, `None` until it is first shown
+    zoom: Option<f64>,
+    watermark: WatermarkSettings,
+    watermark_editing: bool,
+    export_scale: ExportScale,
+    export_scale_editing: bool,
+    beautify: Beautify,
+    beautify_editing: bool,
+    encode: EncodeSettings,
+    adjustments: Adjustments,
+    adjustments_editing: bool,
+    /// width / height of the crop selection, `None` when it is free
+    crop_aspect: Option<f64>,
+    /// how much the color of the trimmed borders can change, from 0 to 100
+    trim_tolerance: f64,
     crop_screenshot_enabled: bool,
     rename_file_enabled: bool,
+    /// paths of the images saved in this session
+    history: Vector<String>,
+    stitch: StitchSettings,
+    /// paths of the images to combine, in the order they are placed
+    stitch_selected: Vector<String>,
+    compare: CompareSettings,
+    /// paths of the older and the newer image being compared
+    compare_before: String,
+    compare_after: String,
+    /// changed pixels and compared pixels, `None` until both images are read
+    compare_changed: Option<(u64, u64)>,
 }
 
 fn main() {
@@ -88,6 +146,8 @@ fn main() {
     verify_exists_dir(BASE_PATH);
     verify_exists_dir(BASE_PATH_SCREENSHOT);
     verify_exists_dir(BASE_PATH_FAVORITE_SHORTCUT);
+    verify_exists_dir(BASE_PATH_STAMPS);
+    verify_exists_dir(BASE_PATH_SETTINGS);
 
     let default_shortcut: HashSet<Code> = HashSet::<Code>::from([Code::KeyB, Code::KeyA]);
 
@@ -116,12 +176,21 @@ fn main() {
         screenshot_id: Some(*SCREENSHOT_WIDGET_ID),
         color: None,
         colors_window_opened: None,
+        color_picker: ColorPickerState::default(),
         state: State::Start,
         base_path: BASE_PATH_SCREENSHOT.to_string(),
         alert: Alert {
             alert_visible: false,
             alert_message: "".to_string(),
         },
+        compare_alert: Alert {
+            alert_visible: false,
+            alert_message: "".to_string(),
+        },
+        stitch_alert: Alert {
+            alert_visible: false,
+            alert_message: "".to_string(),
+        },
         shortcut_keys: ShortcutKeys {
             favorite_hot_keys: default_shortcut.clone(),
             pressed_hot_keys: HashSet::new(),
@@ -129,8 +198,36 @@ fn main() {
         },
         text_field_zstack: true,
         text_field: "".to_string(),
+        text_style: TextStyle::default(),
+        text_editing: false,
+        stamps_opened: false,
+        blend_mode: BlendMode::Normal,
+        annotation_style: AnnotationStyle::default(),
+        spotlight: SpotlightSettings::default(),
+        spotlight_editing: false,
+        callout_editing: false,
+        callout_connector: true,
+        zoom: None,
+        watermark: read_from_file::<WatermarkSettings>(PATH_WATERMARK).unwrap_or_default(),
+        watermark_editing: false,
+        export_scale: read_from_file::<ExportScale>(PATH_EXPORT_SCALE).unwrap_or_default(),
+        export_scale_editing: false,
+        beautify: read_from_file::<Beautify>(PATH_BEAUTIFY).unwrap_or_default(),
+        beautify_editing: false,
+        encode: read_from_file::<EncodeSettings>(PATH_ENCODE).unwrap_or_default(),
+        adjustments: Adjustments::default(),
+        adjustments_editing: false,
+        crop_aspect: None,
+        trim_tolerance: 10.,
         crop_screenshot_enabled: false,
         rename_file_enabled: false,
+        history: Vector::new(),
+        stitch: StitchSettings::default(),
+        stitch_selected: Vector::new(),
+        compare: CompareSettings::default(),
+        compare_before: "".to_string(),
+        compare_after: "".to_string(),
+        compare_changed: None,
     };
 
     // Reading and deserialization from file to set the favourite shortcut
@@ -152,7 +249,7 @@ fn main() {
         initial_state.shortcut_keys.favorite_hot_keys = default_shortcut.clone();
     }
 
-    let delegate = Delegate;
+    let delegate = Delegate::default();
 
     // start the application
     AppLauncher::with_window(main_window)
@@ -161,7 +258,11 @@ fn main() {
         .expect("Failed to launch application");
 }
 
-struct Delegate;
+#[derive(Default)]
+struct Delegate {
+    /// the open compare window: its view is shared, so there is only one
+    compare_window: Option<WindowId>,
+}
 
 impl AppDelegate<AppState> for Delegate {
     fn event(
@@ -172,6 +273,16 @@ impl AppDelegate<AppState> for Delegate {
         data: &mut AppState,
         _env: &Env,
     ) -> Option<Event> {
+        // holding space lets the zoomed screenshot be dragged
+        if let Event::KeyDown(key) | Event::KeyUp(key) = &event {
+            if key.code == Code::Space {
+                ctx.submit_command(
+                    PAN_KEY
+                        .with(matches!(event, Event::KeyDown(_)))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+            }
+        }
         match event.clone() {
             Event::KeyDown(key) => {
                 data.shortcut_keys.pressed_hot_keys.insert(key.code);
@@ -198,6 +309,7 @@ impl AppDelegate<AppState> for Delegate {
                             convert_code.insert(code.to_string());
                         }
 
+                        verify_exists_dir(BASE_PATH_FAVORITE_SHORTCUT);
                         match write_to_file(PATH_FAVORITE_SHORTCUT, &convert_code) {
                             Ok(_) => data
                                 .alert
@@ -279,6 +391,12 @@ impl AppDelegate<AppState> for Delegate {
         Some(event)
     }
 
+    fn window_removed(&mut self, id: WindowId, _data: &mut AppState, _env: &Env, _ctx: &mut DelegateCtx) {
+        if self.compare_window == Some(id) {
+            self.compare_window = None;
+        }
+    }
+
     fn command(
         &mut self,
         ctx: &mut DelegateCtx,
@@ -313,6 +431,112 @@ impl AppDelegate<AppState> for Delegate {
                 .with_min_size(Size::new(450., 300.));
 
             ctx.new_window(window_aboutus);
+        } else if cmd.is(SHOW_STITCH) {
+            let mut monitors = Screen::get_monitors();
+            monitors.sort_by_key(|monitor| !monitor.is_primary());
+            let index: usize =
+                std::str::FromStr::from_str(data.screen.trim_start_matches(".")).unwrap();
+            let monitor = monitors.get(index).unwrap();
+
+            data.stitch_selected.clear();
+            data.stitch_alert.hide_alert();
+            let candidates = stitch_candidates(&data.history, &data.base_path);
+            let window_stitch = WindowDesc::new(build_stitch_widget(&candidates))
+                .title(LocalizedString::new("Combine Images"))
+                .set_always_on_top(false)
+                .transparent(true)
+                .resizable(true)
+                .show_titlebar(true)
+                .window_size((620., 560.))
+                .set_position(monitor.virtual_rect().origin())
+                .with_min_size(Size::new(560., 400.));
+
+            ctx.new_window(window_stitch);
+        } else if let Some(file_info) = cmd.get(SAVE_STITCH) {
+            let mut path = file_info.path.clone();
+            let mut format = match image::ImageFormat::from_path(&path) {
+                Ok(format) if is_save_format(format) => format,
+                _ => image::ImageFormat::Png,
+            };
+            // only some formats keep the transparent background
+            let forced_png = data.stitch.needs_alpha() && !keeps_alpha(format);
+            if forced_png {
+                format = image::ImageFormat::Png;
+            }
+            path.set_extension(format.extensions_str().first().unwrap());
+
+            let combined = data
+                .stitch_selected
+                .iter()
+                .map(|path| image::open(path).map_err(|_| format!("The image {} can't be read!", path)))
+                .collect::<Result<Vec<DynamicImage>, String>>()
+                .and_then(|images| {
+                    stitch(&images, &data.stitch).ok_or("Choose the images to combine!".to_string())
+                });
+            match combined {
+                Ok(combined) => match save_image(&combined, &path, format, &data.encode) {
+                    Ok(_) if forced_png => data.stitch_alert.show_alert(
+                        "The background is transparent: the combined image has been saved as PNG!",
+                    ),
+                    Ok(_) => data.stitch_alert.show_alert("The combined image has been saved on the disk!"),
+                    Err(e) => data
+                        .stitch_alert
+                        .show_alert(&format!("The combined image has not been saved: {}", e)),
+                },
+                Err(e) => data.stitch_alert.show_alert(&e),
+            }
+            return Handled::Yes;
+        } else if cmd.is(SHOW_COMPARE) {
+            if let Some(window_id) = self.compare_window {
+                ctx.submit_command(sys_cmd::SHOW_WINDOW.to(window_id));
+                return Handled::Yes;
+            }
+            let mut monitors = Screen::get_monitors();
+            monitors.sort_by_key(|monitor| !monitor.is_primary());
+            let index: usize =
+                std::str::FromStr::from_str(data.screen.trim_start_matches(".")).unwrap();
+            let monitor = monitors.get(index).unwrap();
+
+            // the compare view starts empty, with the default settings
+            data.compare = CompareSettings::default();
+            data.compare_before = "".to_string();
+            data.compare_after = "".to_string();
+            data.compare_changed = None;
+            data.compare_alert.hide_alert();
+            let window_compare = WindowDesc::new(build_compare_widget())
+                .title(LocalizedString::new("Compare Images"))
+                .set_always_on_top(false)
+                .transparent(true)
+                .resizable(true)
+                .show_titlebar(true)
+                .window_size((800., 600.))
+                .set_position(monitor.virtual_rect().origin())
+                .with_min_size(Size::new(640., 400.));
+
+            self.compare_window = Some(window_compare.id);
+            ctx.new_window(window_compare);
+        } else if let Some(file_info) = cmd.get(COMPARE_BEFORE) {
+            data.compare_before = file_info.path.to_string_lossy().to_string();
+            ctx.submit_command(
+                LOAD_COMPARE_IMAGE
+                    .with((CompareSide::Before, data.compare_before.clone()))
+                    .to(Target::Widget(*COMPARE_WIDGET_ID)),
+            );
+            return Handled::Yes;
+        } else if let Some(file_info) = cmd.get(COMPARE_AFTER) {
+            data.compare_after = file_info.path.to_string_lossy().to_string();
+            ctx.submit_command(
+                LOAD_COMPARE_IMAGE
+                    .with((CompareSide::After, data.compare_after.clone()))
+                    .to(Target::Widget(*COMPARE_WIDGET_ID)),
+            );
+            return Handled::Yes;
+        } else if let Some(changed) = cmd.get(COMPARE_CHANGED) {
+            data.compare_changed = Some(*changed);
+            return Handled::Yes;
+        } else if let Some(message) = cmd.get(SHOW_COMPARE_ALERT) {
+            data.compare_alert.show_alert(message);
+            return Handled::Yes;
         } else if cmd.is(SHORTCUT_KEYS) {
             let mut monitors = Screen::get_monitors();
             monitors.sort_by_key(|monitor| !monitor.is_primary());
@@ -331,6 +555,66 @@ impl AppDelegate<AppState> for Delegate {
                 .with_min_size(Size::new(500., 450.));
 
             ctx.new_window(window_shortcut);
+        } else if cmd.is(COLOR_PICKED) {
+            data.color_picker.set_color(*cmd.get_unchecked(COLOR_PICKED));
+            return Handled::Yes;
+        } else if cmd.is(SHOW_ALERT) {
+            data.alert.show_alert(cmd.get_unchecked(SHOW_ALERT));
+            return Handled::Yes;
+        } else if let Some(text) = cmd.get(ANNOTATION_SELECTED) {
+            // a placed annotation is being edited again, a text one also in the text bar
+            if let Some((text, text_style)) = text {
+                data.text_field = text.clone();
+                data.text_style = text_style.clone();
+                data.text_editing = true;
+            }
+            data.state = State::ScreenTaken(ImageModified::Savable);
+            return Handled::Yes;
+        } else if let Some(blend_mode) = cmd.get(BLEND_MODE_CHANGED) {
+            data.blend_mode = *blend_mode;
+            return Handled::Yes;
+        } else if let Some(annotation_style) = cmd.get(ANNOTATION_STYLE_CHANGED) {
+            data.annotation_style = *annotation_style;
+            return Handled::Yes;
+        } else if let Some(zoom) = cmd.get(ZOOM_CHANGED) {
+            data.zoom = Some(*zoom);
+            return Handled::Yes;
+        } else if let Some(file_info) = cmd.get(SAVE_PROJECT) {
+            let mut path = file_info.path.to_string_lossy().to_string();
+            if !path.ends_with(&format!(".{}", PROJECT_EXTENSION)) {
+                path.push_str(&format!(".{}", PROJECT_EXTENSION));
+            }
+            ctx.submit_command(
+                WRITE_PROJECT
+                    .with((path, data.name.clone(), data.extension.clone()))
+                    .to(Target::Widget(*ZSTACK_ID)),
+            );
+            return Handled::Yes;
+        } else if let Some(file_info) = cmd.get(OPEN_PROJECT) {
+            let path = file_info.path.to_string_lossy().to_string();
+            // the version is checked first, a newer project may not be readable at all
+            let newer = read_from_file::<ProjectHeader>(&path).map_or(false, |header| !header.is_supported());
+            if newer {
+                data.alert.show_alert(
+                    "The project has been saved by a newer version of the application, it can't be opened!",
+                );
+                return Handled::Yes;
+            }
+            match read_from_file::<Project>(&path) {
+                Some(project) => {
+                    data.name = project.metadata.name.clone();
+                    data.extension = project.metadata.extension.clone();
+                    (data.color, data.alpha) = project.color();
+                    data.spotlight = project.spotlight().1;
+                    data.adjustments = project.adjustments();
+                    data.text_field = "".to_string();
+                    data.text_editing = false;
+                    data.state = State::ScreenTaken(ImageModified::NotSavable);
+                    ctx.submit_command(LOAD_PROJECT.with(project).to(Target::Widget(*ZSTACK_ID)));
+                }
+                None => data.alert.show_alert("The file is not a valid project!"),
+            }
+            return Handled::Yes;
         }
         Handled::No
     }
@@ -490,18 +774,21 @@ fn build_shortcut_keys_widget() -> impl Widget<AppState> {
     )
 }
 
-fn alert_widget() -> impl Widget<AppState> {
+/**
+* This function creates the alert bar of a window, shown only while the alert is visible.
+*/
+fn alert_widget(width: f64) -> impl Widget<Alert> {
     let alert = Flex::row()
         .with_child(
-            Label::new(|data: &AppState, _env: &_| data.alert.alert_message.clone())
+            Label::new(|data: &Alert, _env: &_| data.alert_message.clone())
                 .with_text_color(Color::WHITE)
                 .padding(10.0),
         )
         .with_default_spacer()
         .with_child(
             Button::new("Close")
-                .on_click(|ctx, data: &mut AppState, _| {
-                    data.alert.hide_alert();
+                .on_click(|ctx, data: &mut Alert, _| {
+                    data.hide_alert();
                     ctx.request_update();
                 })
                 .fix_height(30.0)
@@ -510,10 +797,10 @@ fn alert_widget() -> impl Widget<AppState> {
         .with_default_spacer()
         .background(Color::rgb8(0, 120, 200))
         .border(Color::rgb8(0, 100, 160), 2.0)
-        .fix_width(800.0)
+        .fix_width(width)
         .fix_height(40.0)
         .center();
-    alert
+    Either::new(|data: &Alert, _| data.alert_visible, alert, Label::new(""))
 }
 
 fn build_screenshot_widget(monitor: usize) -> impl Widget<AppState> {
@@ -527,37 +814,26 @@ fn build_screenshot_widget(monitor: usize) -> impl Widget<AppState> {
     )
     .with_color(Color::rgb8(70, 250, 70).with_alpha(1.))
     .on_click(|ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
-        data.shortcut_keys.state = StateShortcutKeys::NotBusy; // reset of shortcut state
-
-        if data.state != State::Start {
-            data.name = "".to_string(); // reset name file
-        }
-        let (base_path, name) = file_name(data.name.clone(), data.base_path.clone());
-        data.name = (*name.clone()).to_string();
+        take_screenshot(ctx, data, false);
+    });
 
-        ctx.submit_command(
-            SAVE_SCREENSHOT
-                .with((
-                    data.rect,
-                    data.main_window_id.expect("How did you open this window?"),
-                    data.custom_zstack_id
-                        .expect("How did you open this window?"),
-                    data.screenshot_id.expect("How did you open this window?"),
-                    base_path,
-                    name,
-                    image::ImageFormat::from_extension(data.extension.trim_start_matches("."))
-                        .unwrap(),
-                    data.delay as u64,
-                    std::str::FromStr::from_str(data.screen.trim_start_matches(".")).unwrap(),
-                ))
-                .to(Target::Widget(ctx.widget_id())),
-        );
-        data.state = State::ScreenTaken(ImageModified::NotSavable);
-        data.delay = 0.;
-        data.alert
-            .show_alert("The image has been saved on the disk!");
+    let scrolling_capture_button = TakeScreenshotButton::from_label(
+        Label::new("Scrolling Capture")
+            .with_text_color(Color::BLACK)
+            .with_font(FontDescriptor::new(FontFamily::MONOSPACE))
+            .with_text_size(20.),
+    )
+    .with_color(Color::rgb8(70, 200, 250).with_alpha(1.))
+    .on_click(|ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+        take_screenshot(ctx, data, true);
     });
 
+    let scrolling_hint = Label::new(
+        "Scrolling capture: scroll the page down slowly, it stops when the page stops moving or on Esc",
+    )
+    .with_text_color(Color::WHITE)
+    .background(Color::BLACK.with_alpha(0.55));
+
     let delay_value = Label::dynamic(|data: &AppState, _env| data.delay.to_string())
         .with_text_color(Color::WHITE)
         .background(Color::BLACK.with_alpha(0.55));
@@ -590,11 +866,19 @@ fn build_screenshot_widget(monitor: usize) -> impl Widget<AppState> {
     let buttons_flex = Flex::row()
         .with_child(take_screenshot_button)
         .with_default_spacer()
+        .with_child(scrolling_capture_button)
+        .with_default_spacer()
         .with_child(delay_value)
         .with_child(delay_stepper)
         .with_default_spacer()
         .with_child(close_button);
 
+    let buttons_flex = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::End)
+        .with_child(buttons_flex)
+        .with_default_spacer()
+        .with_child(scrolling_hint);
+
     let zstack = ZStack::new(rectangle).with_child(
         buttons_flex,
         Vec2::new(1.0, 1.0),
@@ -606,12 +890,63 @@ fn build_screenshot_widget(monitor: usize) -> impl Widget<AppState> {
     zstack
 }
 
+/**
+* This function starts the capture of the selected area, a scrolling capture joins the frames taken
+* while the page is scrolled.
+*/
+fn take_screenshot(ctx: &mut EventCtx, data: &mut AppState, scrolling: bool) {
+    data.shortcut_keys.state = StateShortcutKeys::NotBusy; // reset of shortcut state
+
+    if data.state != State::Start {
+        data.name = "".to_string(); // reset name file
+    }
+    data.adjustments = Adjustments::default(); // the ZStack drops them with the old screenshot
+    let (base_path, name) = file_name(data.name.clone(), data.base_path.clone());
+    data.name = (*name.clone()).to_string();
+    data.history.push_back(format!(
+        "{}{}.{}",
+        base_path,
+        name,
+        data.extension.trim_start_matches(".")
+    ));
+
+    let selector = if scrolling { SCROLL_CAPTURE } else { SAVE_SCREENSHOT };
+    ctx.submit_command(
+        selector
+            .with((
+                data.rect,
+                data.main_window_id.expect("How did you open this window?"),
+                data.custom_zstack_id
+                    .expect("How did you open this window?"),
+                data.screenshot_id.expect("How did you open this window?"),
+                base_path,
+                name,
+                image::ImageFormat::from_extension(data.extension.trim_start_matches("."))
+                    .unwrap(),
+                data.delay as u64,
+                std::str::FromStr::from_str(data.screen.trim_start_matches(".")).unwrap(),
+            ))
+            .to(Target::Widget(ctx.widget_id())),
+    );
+    data.state = State::ScreenTaken(ImageModified::NotSavable);
+    data.delay = 0.;
+    // the scrolling capture tells how it went once it is over
+    if !scrolling {
+        data.alert
+            .show_alert("The image has been saved on the disk!");
+    }
+}
+
 fn build_screenshot_crop_widget(monitor: usize) -> impl Widget<AppState> {
     let rectangle = LensWrap::new(SelectedRect::new(monitor), AppState::rect);
     Container::new(rectangle)
 }
 
 fn build_root_widget() -> impl Widget<AppState> {
+    let (stamps, unreadable_stamps) = load_stamps(BASE_PATH_STAMPS);
+    let stamps_images: Vec<DynamicImage> = stamps.iter().map(|(_, stamp)| stamp.clone()).collect();
+    let stamps_images_crop = stamps_images.clone();
+
     let take_screenshot_button = Either::new(
         |data: &AppState, _env| data.crop_screenshot_enabled == false,
         ColoredButton::from_label(Label::new(|data: &AppState, _env: &_| match data.state {
@@ -699,13 +1034,27 @@ fn build_root_widget() -> impl Widget<AppState> {
         CustomZStack::new(screenshot_image, *SCREENSHOT_WIDGET_ID),
         *ZSTACK_ID,
     )
-    .on_added(move |_this, ctx, _data: &AppState, _env| {
-        let mut args = Vec::<&'static str>::new();
-        args.push("./src/images/icons/red-circle.png");
-        args.push("./src/images/icons/triangle.png");
-        args.push("./src/images/icons/red-arrow.png");
-        args.push("./src/images/icons/highlighter.png");
-        ctx.submit_command(CREATE_ZSTACK.with(args).to(Target::Widget(*ZSTACK_ID)));
+    .on_added(move |_this, ctx, data: &AppState, _env| {
+        ctx.submit_command(SET_WATERMARK.with(data.watermark.clone()).to(Target::Widget(*ZSTACK_ID)));
+        ctx.submit_command(SET_EXPORT_SCALE.with(data.export_scale).to(Target::Widget(*ZSTACK_ID)));
+        ctx.submit_command(SET_BEAUTIFY.with(data.beautify).to(Target::Widget(*ZSTACK_ID)));
+        ctx.submit_command(SET_ENCODE.with(data.encode).to(Target::Widget(*ZSTACK_ID)));
+        let mut args = Vec::<String>::new();
+        args.push("./src/images/icons/red-circle.png".to_string());
+        args.push("./src/images/icons/triangle.png".to_string());
+        args.push("./src/images/icons/red-arrow.png".to_string());
+        args.push("./src/images/icons/highlighter.png".to_string());
+        ctx.submit_command(
+            CREATE_ZSTACK
+                .with((args, stamps_images.clone()))
+                .to(Target::Widget(*ZSTACK_ID)),
+        );
+        if !unreadable_stamps.is_empty() {
+            ctx.submit_command(SHOW_ALERT.with(format!(
+                "These stamps can't be read and have been skipped: {}",
+                unreadable_stamps.join(", ")
+            )));
+        }
     });
 
     let screenshot_image_crop = IdentityWrapper::wrap(
@@ -744,13 +1093,21 @@ fn build_root_widget() -> impl Widget<AppState> {
         CustomZStack::new(screenshot_image_crop, *SCREENSHOT_WIDGET_ID),
         *ZSTACK_ID,
     )
-    .on_added(move |_this, ctx, _data: &AppState, _env| {
-        let mut args = Vec::<&'static str>::new();
-        args.push("./src/images/icons/red-circle.png");
-        args.push("./src/images/icons/triangle.png");
-        args.push("./src/images/icons/red-arrow.png");
-        args.push("./src/images/icons/highlighter.png");
-        ctx.submit_command(CREATE_ZSTACK.with(args).to(Target::Widget(*ZSTACK_ID)));
+    .on_added(move |_this, ctx, data: &AppState, _env| {
+        ctx.submit_command(SET_WATERMARK.with(data.watermark.clone()).to(Target::Widget(*ZSTACK_ID)));
+        ctx.submit_command(SET_EXPORT_SCALE.with(data.export_scale).to(Target::Widget(*ZSTACK_ID)));
+        ctx.submit_command(SET_BEAUTIFY.with(data.beautify).to(Target::Widget(*ZSTACK_ID)));
+        ctx.submit_command(SET_ENCODE.with(data.encode).to(Target::Widget(*ZSTACK_ID)));
+        let mut args = Vec::<String>::new();
+        args.push("./src/images/icons/red-circle.png".to_string());
+        args.push("./src/images/icons/triangle.png".to_string());
+        args.push("./src/images/icons/red-arrow.png".to_string());
+        args.push("./src/images/icons/highlighter.png".to_string());
+        ctx.submit_command(
+            CREATE_ZSTACK
+                .with((args, stamps_images_crop.clone()))
+                .to(Target::Widget(*ZSTACK_ID)),
+        );
     });
 
     let spaced_zstack = Either::new(
@@ -770,20 +1127,13 @@ fn build_root_widget() -> impl Widget<AppState> {
         Container::new(zstack).padding((10.0, 10.0)),
     );
 
-    let crop_screenshot_save_button = TakeScreenshotButton::from_label(Label::new("Update Image"))
+    let crop_screenshot_save_button = TakeScreenshotButton::from_label(Label::new("Apply"))
         .with_color(Color::rgb8(0, 150, 0).with_alpha(1.))
         .on_click(|ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
-            let (base_path, name) = file_name(data.name.clone(), data.base_path.clone());
-            data.name = (*name.clone()).to_string();
-
             ctx.submit_command(
                 UPDATE_SCREENSHOT_CROP
                     .with((
                         data.rect,
-                        base_path,
-                        name,
-                        image::ImageFormat::from_extension(data.extension.trim_start_matches("."))
-                            .unwrap(),
                         data.custom_zstack_id
                             .expect("How did you open this window?"),
                     ))
@@ -796,7 +1146,7 @@ fn build_root_widget() -> impl Widget<AppState> {
             data.shortcut_keys.pressed_hot_keys = HashSet::new(); // clean map
 
             data.alert
-                .show_alert("The image has been saved on the disk!");
+                .show_alert("The crop is applied on export, the original capture is kept until then.");
 
             data.state = State::ScreenTaken(ImageModified::NotSavable);
         });
@@ -807,6 +1157,7 @@ fn build_root_widget() -> impl Widget<AppState> {
             data.crop_screenshot_enabled = false; // disable the crop screenshot widget
             data.state = State::ScreenTaken(ImageModified::NotSavable);
 
+            ctx.submit_command(CANCEL_CROP.to(Target::Widget(*ZSTACK_ID)));
             ctx.submit_command(
                 UPDATE_SCREENSHOT_CROP_CLOSE.to(Target::Widget(*SCREENSHOT_WIDGET_ID)),
             );
@@ -815,15 +1166,54 @@ fn build_root_widget() -> impl Widget<AppState> {
             data.shortcut_keys.state = StateShortcutKeys::NotBusy; // it has finished its job
         });
 
+    let reset_crop_button = ColoredButton::from_label(Label::new("Reset to original"))
+        .with_color(Color::rgb8(90, 90, 90).with_alpha(1.))
+        .on_click(|ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            data.crop_screenshot_enabled = false;
+            data.state = State::ScreenTaken(ImageModified::NotSavable);
+
+            ctx.submit_command(RESET_CROP.to(Target::Widget(*ZSTACK_ID)));
+
+            data.shortcut_keys.pressed_hot_keys = HashSet::new(); // clean map
+            data.shortcut_keys.state = StateShortcutKeys::NotBusy; // it has finished its job
+        });
+
     let buttons_crop_screenshot_flex = Flex::row()
         .with_child(
             Label::new("Crop Screenshot:  ")
                 .with_text_color(Color::BLACK)
                 .padding(2.),
         )
+        .with_child(option_button(
+            "Free",
+            AppState::crop_aspect,
+            |aspect| aspect.is_none(),
+            |aspect| *aspect = None,
+        ))
+        .with_child(option_button(
+            "1:1",
+            AppState::crop_aspect,
+            |aspect| *aspect == Some(1.),
+            |aspect| *aspect = Some(1.),
+        ))
+        .with_child(option_button(
+            "4:3",
+            AppState::crop_aspect,
+            |aspect| *aspect == Some(4. / 3.),
+            |aspect| *aspect = Some(4. / 3.),
+        ))
+        .with_child(option_button(
+            "16:9",
+            AppState::crop_aspect,
+            |aspect| *aspect == Some(16. / 9.),
+            |aspect| *aspect = Some(16. / 9.),
+        ))
+        .with_default_spacer()
         .with_child(crop_screenshot_save_button)
         .with_default_spacer()
-        .with_child(close_crop_screenshop_button);
+        .with_child(close_crop_screenshop_button)
+        .with_default_spacer()
+        .with_child(reset_crop_button);
 
     let crop_screenshot_button = Either::new(
         |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
@@ -841,6 +1231,10 @@ fn build_root_widget() -> impl Widget<AppState> {
 
                     data.crop_screenshot_enabled = true;
                     data.state = State::ScreenTaken(ImageModified::Savable);
+
+                    // the whole capture is shown again, the crop can be changed until the export
+                    ctx.submit_command(START_CROP.to(Target::Widget(*ZSTACK_ID)));
+                    ctx.submit_command(SET_CROP_ASPECT.with(data.crop_aspect));
                 }),
             Label::new(""),
         ),
@@ -871,71 +1265,37 @@ fn build_root_widget() -> impl Widget<AppState> {
         },
         ViewSwitcher::new(
             |data: &AppState, _env| data.clone(),
-            |selector, _data, _env| match selector.extension.as_str() {
-                "png" => Box::new(
-                    Label::new("PNG ▼")
-                        .with_text_color(Color::BLACK)
-                        .border(Color::BLACK, 2.)
-                        .on_click(|_, data: &mut AppState, _| {
-                            data.extension = String::from(".png")
-                        }),
-                ),
-                "jpg" => Box::new(
-                    Label::new("JPG ▼")
-                        .with_text_color(Color::BLACK)
-                        .border(Color::BLACK, 2.)
-                        .on_click(|_, data: &mut AppState, _| {
-                            data.extension = String::from(".jpg")
-                        }),
-                ),
-                "gif" => Box::new(
-                    Label::new("GIF ▼")
-                        .with_text_color(Color::BLACK)
-                        .border(Color::BLACK, 2.)
-                        .on_click(|_, data: &mut AppState, _| {
-                            data.extension = String::from(".gif")
-                        }),
-                ),
-                _ => {
-                    let text_alpha = match selector.extension.as_str() {
-                        ".png" => (1f64, 0.4f64, 0.4f64),
-                        ".jpg" => (0.4f64, 1f64, 0.4f64),
-                        ".gif" => (0.4f64, 0.4f64, 1f64),
-                        _ => panic!(),
-                    };
-                    Box::new(
-                        Scroll::new(
-                            Flex::column()
-                                .with_child(
-                                    Label::new("PNG")
-                                        .with_text_color(Color::BLACK.with_alpha(text_alpha.0))
-                                        .border(Color::BLACK.with_alpha(text_alpha.0), 2.)
-                                        .fix_size(40., 27.)
-                                        .on_click(|_, data: &mut AppState, _| {
-                                            data.extension = String::from("png")
-                                        }),
-                                )
-                                .with_child(
-                                    Label::new("JPG")
-                                        .with_text_color(Color::BLACK.with_alpha(text_alpha.1))
-                                        .border(Color::BLACK.with_alpha(text_alpha.1), 2.)
-                                        .fix_size(40., 27.)
-                                        .on_click(|_, data: &mut AppState, _| {
-                                            data.extension = String::from("jpg")
-                                        }),
-                                )
-                                .with_child(
-                                    Label::new("GIF")
-                                        .with_text_color(Color::BLACK.with_alpha(text_alpha.2))
-                                        .border(Color::BLACK.with_alpha(text_alpha.2), 2.)
-                                        .fix_size(40., 27.)
-                                        .on_click(|_, data: &mut AppState, _| {
-                                            data.extension = String::from("gif")
-                                        }),
-                                ),
-                        )
-                        .border(Color::BLACK.with_alpha(0.6), 4.),
-                    )
+            |selector, _data, _env| {
+                // the chosen format is stored without the dot while the list is closed
+                match SAVE_FORMATS.iter().copied().find(|(_, extension)| *extension == selector.extension) {
+                    Some((name, extension)) => Box::new(
+                        Label::new(format!("{} ▼", name))
+                            .with_text_color(Color::BLACK)
+                            .border(Color::BLACK, 2.)
+                            .on_click(move |_, data: &mut AppState, _| {
+                                data.extension = format!(".{}", extension)
+                            }),
+                    ),
+                    None => {
+                        let mut formats = Flex::column();
+                        for &(name, extension) in SAVE_FORMATS {
+                            let text_alpha = if selector.extension.trim_start_matches(".") == extension {
+                                1f64
+                            } else {
+                                0.4f64
+                            };
+                            formats.add_child(
+                                Label::new(name)
+                                    .with_text_color(Color::BLACK.with_alpha(text_alpha))
+                                    .border(Color::BLACK.with_alpha(text_alpha), 2.)
+                                    .fix_size(48., 27.)
+                                    .on_click(move |_, data: &mut AppState, _| {
+                                        data.extension = String::from(extension)
+                                    }),
+                            );
+                        }
+                        Box::new(Scroll::new(formats).border(Color::BLACK.with_alpha(0.6), 4.))
+                    }
                 }
             },
         ),
@@ -1044,111 +1404,963 @@ fn build_root_widget() -> impl Widget<AppState> {
         Label::new(""),
     );
 
+    let redact_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("▮")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Redact, None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.state = State::ScreenTaken(ImageModified::Savable);
+            },
+        ),
+        Label::new(""),
+    );
+
+    let marker_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("①")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Marker, None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.state = State::ScreenTaken(ImageModified::Savable);
+            },
+        ),
+        Label::new(""),
+    );
+
+    let text_style_bar = Flex::column()
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Size:").with_text_color(Color::BLACK))
+                .with_child(
+                    Label::dynamic(|data: &AppState, _env| data.text_style.size.to_string())
+                        .with_text_color(Color::BLACK),
+                )
+                .with_child(
+                    Stepper::new()
+                        .with_range(10.0, 120.0)
+                        .with_step(2.0)
+                        .lens(TextStyle::size)
+                        .lens(AppState::text_style),
+                ),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(
+                    labeled_checkbox("Bold")
+                        .lens(TextStyle::bold)
+                        .lens(AppState::text_style),
+                )
+                .with_child(
+                    labeled_checkbox("Italic")
+                        .lens(TextStyle::italic)
+                        .lens(AppState::text_style),
+                ),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(
+                    labeled_checkbox("Box")
+                        .lens(TextStyle::background)
+                        .lens(AppState::text_style),
+                )
+                .with_child(
+                    labeled_checkbox("Outline")
+                        .lens(TextStyle::outline)
+                        .lens(AppState::text_style),
+                ),
+        );
+
     let text_field = Either::new(
         |data: &AppState, _| {
-            data.text_field_zstack == true
-                && data.state == State::ScreenTaken(ImageModified::NotSavable)
+            (data.text_field_zstack == true
+                && data.state == State::ScreenTaken(ImageModified::NotSavable))
+                || data.text_editing == true
         },
         Flex::row()
             .with_child(
-                druid::widget::TextBox::new()
+                druid::widget::TextBox::multiline()
                     .with_placeholder("Enter a text")
                     .fix_width(150.)
+                    .fix_height(60.)
                     .lens(AppState::text_field),
             )
             .with_default_spacer()
+            .with_child(text_style_bar)
+            .with_default_spacer()
             .with_child(
-                Button::from_label(Label::new("Save"))
-                    .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                Button::from_label(Label::new(|data: &AppState, _env: &_| {
+                    if data.text_editing {
+                        "Update"
+                    } else {
+                        "Save"
+                    }
+                }))
+                .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                    if data.text_editing {
+                        // the placed text is rendered again with the new text and style
+                        ctx.submit_command(
+                            UPDATE_OVER_TEXT
+                                .with((data.text_field.clone(), data.text_style.clone()))
+                                .to(Target::Widget(*ZSTACK_ID)),
+                        );
+                    } else {
                         ctx.submit_command(
                             SHOW_OVER_IMG
-                                .with((OverImages::Text, Some(data.text_field.clone())))
+                                .with((
+                                    OverImages::Text,
+                                    Some((data.text_field.clone(), data.text_style.clone())),
+                                ))
                                 .to(Target::Widget(*ZSTACK_ID)),
                         );
-                        data.text_field = "".to_string();
                         data.text_field_zstack = false;
+                        data.text_editing = true;
                         data.state = State::ScreenTaken(ImageModified::Savable);
-                        ctx.request_update();
-                    })
-                    .disabled_if(move |data: &AppState, _env: &Env| {
-                        return data.text_field.len() == 0;
-                    }),
+                    }
+                    ctx.request_update();
+                })
+                .disabled_if(move |data: &AppState, _env: &Env| {
+                    return data.text_field.len() == 0;
+                }),
             )
             .with_default_spacer()
             .with_child(Button::from_label(Label::new("Cancel")).on_click(
                 move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
                     data.text_field = "".to_string();
                     data.text_field_zstack = false;
+                    data.text_editing = false;
                     ctx.request_update();
                 },
             )),
         Label::new(""),
     );
 
-    let text_button = Either::new(
+    let stroke_button = Either::new(
         |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
-        Button::from_label(Label::new("Text")).on_click(
+        Button::from_label(Label::new("🖍")).on_click(
             move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
-                data.text_field_zstack = true;
-                ctx.submit_command(sys_cmd::SHOW_ALL);
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Stroke(StrokeShape::Freehand), None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.state = State::ScreenTaken(ImageModified::Savable);
             },
         ),
         Label::new(""),
     );
 
-    let colors_button = Either::new(
+    let stroke_rect_button = Either::new(
         |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
-        ColoredButton::from_label(Label::new("Color"))
-            .with_color(Color::PURPLE)
-            .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
-                if data.colors_window_opened.is_none() {
-                    let mut init_pos = ctx.to_screen(Point::new(1., ctx.size().height - 1.));
-                    init_pos.y += 5.;
-                    let wd = WindowDesc::new(build_colors_window(*ZSTACK_ID))
-                        .title(WINDOW_TITLE)
-                        .set_always_on_top(true)
-                        .show_titlebar(false)
-                        .set_window_state(WindowState::Restored)
-                        .window_size((1., 250.))
-                        .set_position(init_pos)
-                        .resizable(false)
-                        .transparent(false);
-                    data.colors_window_opened = Some(wd.id);
-                    ctx.new_window(wd);
-                } else {
-                    ctx.get_external_handle()
-                        .submit_command(
-                            sys_cmd::CLOSE_WINDOW,
-                            (),
-                            Window(data.colors_window_opened.unwrap()),
-                        )
-                        .unwrap();
-                    data.colors_window_opened = None;
-                }
-            }),
-        Label::new(""),
-    );
-
-    let remove_over_img = Either::new(
-        |data: &AppState, _env| {
-            data.state == State::ScreenTaken(ImageModified::Savable)
-                && data.crop_screenshot_enabled == false
-                && data.rename_file_enabled == false
-        },
-        Button::from_label(Label::new("❌")).on_click(
+        Button::from_label(Label::new("🖍▭")).on_click(
             move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
                 ctx.submit_command(
                     SHOW_OVER_IMG
-                        .with((OverImages::Remove, None))
+                        .with((OverImages::Stroke(StrokeShape::Rectangle), None))
                         .to(Target::Widget(*ZSTACK_ID)),
                 );
-                data.state = State::ScreenTaken(ImageModified::NotSavable);
+                data.state = State::ScreenTaken(ImageModified::Savable);
             },
         ),
         Label::new(""),
     );
-    let path_button = Either::new(
-        |data: &AppState, _env| {
+
+    let spotlight_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("🔦")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Spotlight, None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.spotlight_editing = true;
+                data.state = State::ScreenTaken(ImageModified::Savable);
+            },
+        ),
+        Label::new(""),
+    );
+
+    let spotlight_bar = Either::new(
+        |data: &AppState, _env| {
+            data.spotlight_editing == true
+                && data.state == State::ScreenTaken(ImageModified::Savable)
+        },
+        Flex::row()
+            .with_child(option_button(
+                "▭",
+                AppState::spotlight,
+                |spotlight| spotlight.shape == SpotlightShape::Rectangle,
+                |spotlight| spotlight.shape = SpotlightShape::Rectangle,
+            ))
+            .with_child(option_button(
+                "◯",
+                AppState::spotlight,
+                |spotlight| spotlight.shape == SpotlightShape::Ellipse,
+                |spotlight| spotlight.shape = SpotlightShape::Ellipse,
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Darken",
+                AppState::spotlight,
+                |spotlight| spotlight.effect == SpotlightEffect::Darken,
+                |spotlight| spotlight.effect = SpotlightEffect::Darken,
+            ))
+            .with_child(option_button(
+                "Blur",
+                AppState::spotlight,
+                |spotlight| spotlight.effect == SpotlightEffect::Blur,
+                |spotlight| spotlight.effect = SpotlightEffect::Blur,
+            ))
+            .with_default_spacer()
+            .with_child(stepper(
+                AppState::spotlight.then(SpotlightSettings::amount),
+                10.,
+                (0., 100.),
+                |data: &AppState, _env| format!("{}%", data.spotlight.amount),
+            )),
+        Label::new(""),
+    );
+
+    let callout_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("🔍")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Callout, None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.callout_editing = true;
+                data.state = State::ScreenTaken(ImageModified::Savable);
+            },
+        ),
+        Label::new(""),
+    );
+
+    let callout_bar = Either::new(
+        |data: &AppState, _env| {
+            data.callout_editing == true
+                && data.state == State::ScreenTaken(ImageModified::Savable)
+        },
+        Button::from_label(Label::new(|data: &AppState, _env: &_| {
+            if data.callout_connector {
+                "[Line]".to_string()
+            } else {
+                "Line".to_string()
+            }
+        }))
+        .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            data.callout_connector = !data.callout_connector;
+            ctx.submit_command(
+                SET_CALLOUT_CONNECTOR
+                    .with(data.callout_connector)
+                    .to(Target::Widget(*ZSTACK_ID)),
+            );
+        }),
+        Label::new(""),
+    );
+
+    let measure_line_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("📏")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Measure(MeasureShape::Line), None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.state = State::ScreenTaken(ImageModified::Savable);
+            },
+        ),
+        Label::new(""),
+    );
+
+    let measure_box_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("📐")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Measure(MeasureShape::Box), None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.state = State::ScreenTaken(ImageModified::Savable);
+            },
+        ),
+        Label::new(""),
+    );
+
+    let blend_bar = Either::new(
+        |data: &AppState, _env| {
+            data.state == State::ScreenTaken(ImageModified::Savable)
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Flex::row()
+            .with_child(Label::new("Blend:").with_text_color(Color::BLACK))
+            .with_child(option_button(
+                "Normal",
+                AppState::blend_mode,
+                |mode| *mode == BlendMode::Normal,
+                |mode| *mode = BlendMode::Normal,
+            ))
+            .with_child(option_button(
+                "Multiply",
+                AppState::blend_mode,
+                |mode| *mode == BlendMode::Multiply,
+                |mode| *mode = BlendMode::Multiply,
+            ))
+            .with_child(option_button(
+                "Darken",
+                AppState::blend_mode,
+                |mode| *mode == BlendMode::Darken,
+                |mode| *mode = BlendMode::Darken,
+            )),
+        Label::new(""),
+    );
+
+    let style_bar = Either::new(
+        |data: &AppState, _env| {
+            data.state == State::ScreenTaken(ImageModified::Savable)
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Flex::row()
+            .with_child(Button::from_label(Label::new("Flip ⇋")).on_click(
+                move |ctx: &mut EventCtx, _data: &mut AppState, _env: &Env| {
+                    ctx.submit_command(FLIP_OVER_IMG.with(Axis::Horizontal).to(Target::Widget(*ZSTACK_ID)));
+                },
+            ))
+            .with_child(Button::from_label(Label::new("Flip ⇅")).on_click(
+                move |ctx: &mut EventCtx, _data: &mut AppState, _env: &Env| {
+                    ctx.submit_command(FLIP_OVER_IMG.with(Axis::Vertical).to(Target::Widget(*ZSTACK_ID)));
+                },
+            ))
+            .with_child(
+                Label::new("drag the dot to rotate, Shift snaps to 15°").with_text_color(Color::BLACK.with_alpha(0.6)),
+            )
+            .with_default_spacer()
+            .with_child(option_button(
+                "Outline",
+                AppState::annotation_style,
+                |style| style.outline,
+                |style| style.outline = !style.outline,
+            ))
+            .with_child(option_button(
+                "White",
+                AppState::annotation_style,
+                |style| style.outline_color == Color::WHITE,
+                |style| style.outline_color = Color::WHITE,
+            ))
+            .with_child(option_button(
+                "Black",
+                AppState::annotation_style,
+                |style| style.outline_color == Color::BLACK,
+                |style| style.outline_color = Color::BLACK,
+            ))
+            .with_child(stepper(
+                AppState::annotation_style.then(AnnotationStyle::outline_width),
+                1.,
+                (1., 20.),
+                |data: &AppState, _env| format!("{}px", data.annotation_style.outline_width),
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Shadow",
+                AppState::annotation_style,
+                |style| style.shadow,
+                |style| style.shadow = !style.shadow,
+            ))
+            .with_child(Label::new("offset").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::annotation_style.then(AnnotationStyle::shadow_offset),
+                1.,
+                (0., 30.),
+                |data: &AppState, _env| format!("{}px", data.annotation_style.shadow_offset),
+            ))
+            .with_child(Label::new("blur").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::annotation_style.then(AnnotationStyle::shadow_blur),
+                1.,
+                (0., 20.),
+                |data: &AppState, _env| format!("{}px", data.annotation_style.shadow_blur),
+            )),
+        Label::new(""),
+    );
+
+    let stamps_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("Stamps")).on_click(
+            move |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                data.stamps_opened = !data.stamps_opened;
+            },
+        ),
+        Label::new(""),
+    );
+
+    let trim_bar = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Flex::row()
+            .with_child(Button::from_label(Label::new("Auto trim")).on_click(
+                move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                    ctx.submit_command(
+                        AUTO_TRIM
+                            .with((data.trim_tolerance, *ZSTACK_ID))
+                            .to(Target::Widget(*SCREENSHOT_WIDGET_ID)),
+                    );
+                },
+            ))
+            .with_child(Button::from_label(Label::new("-")).on_click(
+                |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                    data.trim_tolerance = (data.trim_tolerance - 5.).max(0.);
+                },
+            ))
+            .with_child(
+                Label::dynamic(|data: &AppState, _env| format!("tolerance {}%", data.trim_tolerance))
+                    .with_text_color(Color::BLACK),
+            )
+            .with_child(Button::from_label(Label::new("+")).on_click(
+                |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                    data.trim_tolerance = (data.trim_tolerance + 5.).min(100.);
+                },
+            )),
+        Label::new(""),
+    );
+
+    let zoom_bar = Either::new(
+        |data: &AppState, _env| {
+            matches!(data.state, State::ScreenTaken(_))
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Flex::row()
+            .with_child(orientation_button("Rotate image ⟲", Orientation::RotateLeft))
+            .with_child(orientation_button("Rotate image ⟳", Orientation::RotateRight))
+            .with_child(orientation_button("Flip image ⇋", Orientation::FlipHorizontal))
+            .with_child(orientation_button("Flip image ⇅", Orientation::FlipVertical))
+            .with_default_spacer()
+            .with_child(trim_bar)
+            .with_default_spacer()
+            .with_child(zoom_button("Fit", None))
+            .with_child(zoom_button("100%", Some(1.)))
+            .with_child(zoom_button("200%", Some(2.)))
+            .with_child(
+                Label::dynamic(|data: &AppState, _env| match data.zoom {
+                    Some(zoom) => format!("{:.0}%", zoom * 100.),
+                    None => "".to_string(),
+                })
+                .with_text_color(Color::BLACK),
+            )
+            .with_child(
+                Label::new("Ctrl + wheel to zoom, Space + drag or the middle button to pan")
+                    .with_text_color(Color::BLACK.with_alpha(0.6)),
+            ),
+        Label::new(""),
+    );
+
+    let adjustments_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new(|data: &AppState, _env: &_| {
+            if data.adjustments.is_identity() {
+                "Adjust"
+            } else {
+                "Adjust [On]"
+            }
+        }))
+        .on_click(move |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            data.adjustments_editing = !data.adjustments_editing;
+        }),
+        Label::new(""),
+    );
+
+    let adjustments_bar = Either::new(
+        |data: &AppState, _env| {
+            data.adjustments_editing
+                && matches!(data.state, State::ScreenTaken(_))
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Flex::row()
+            .with_child(Label::new("brightness").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::adjustments.then(Adjustments::brightness),
+                10.,
+                (-100., 100.),
+                |data: &AppState, _env| format!("{}", data.adjustments.brightness),
+            ))
+            .with_child(Label::new("contrast").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::adjustments.then(Adjustments::contrast),
+                10.,
+                (-100., 100.),
+                |data: &AppState, _env| format!("{}", data.adjustments.contrast),
+            ))
+            .with_child(Label::new("saturation").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::adjustments.then(Adjustments::saturation),
+                10.,
+                (-100., 100.),
+                |data: &AppState, _env| format!("{}", data.adjustments.saturation),
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Grayscale",
+                AppState::adjustments,
+                |adjustments| adjustments.grayscale,
+                |adjustments| adjustments.grayscale = !adjustments.grayscale,
+            ))
+            .with_child(option_button(
+                "Invert",
+                AppState::adjustments,
+                |adjustments| adjustments.invert,
+                |adjustments| adjustments.invert = !adjustments.invert,
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Reset",
+                AppState::adjustments,
+                |_| false,
+                |adjustments| *adjustments = Adjustments::default(),
+            )),
+        Label::new(""),
+    );
+
+    let watermark_button = Either::new(
+        |data: &AppState, _env| data.crop_screenshot_enabled == false && data.rename_file_enabled == false,
+        Button::from_label(Label::new(|data: &AppState, _env: &_| {
+            if data.watermark.enabled {
+                "Watermark [On]"
+            } else {
+                "Watermark"
+            }
+        }))
+        .on_click(move |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            data.watermark_editing = !data.watermark_editing;
+        }),
+        Label::new(""),
+    );
+
+    let watermark_bar = Either::new(
+        |data: &AppState, _env| {
+            data.watermark_editing
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Flex::row()
+            .with_child(option_button(
+                "On",
+                AppState::watermark,
+                |watermark| watermark.enabled,
+                |watermark| watermark.enabled = !watermark.enabled,
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Text",
+                AppState::watermark,
+                |watermark| watermark.kind == WatermarkKind::Text,
+                |watermark| watermark.kind = WatermarkKind::Text,
+            ))
+            .with_child(option_button(
+                "Image",
+                AppState::watermark,
+                |watermark| watermark.kind == WatermarkKind::Image,
+                |watermark| watermark.kind = WatermarkKind::Image,
+            ))
+            .with_child(ViewSwitcher::new(
+                |data: &AppState, _env| data.watermark.kind,
+                |kind, _data, _env| match kind {
+                    WatermarkKind::Text => Box::new(
+                        TextBox::new()
+                            .with_placeholder("{date} {time} {user} {monitor}")
+                            .lens(AppState::watermark.then(WatermarkSettings::text))
+                            .fix_width(260.),
+                    ),
+                    WatermarkKind::Image => Box::new(
+                        TextBox::new()
+                            .with_placeholder("image path")
+                            .lens(AppState::watermark.then(WatermarkSettings::image_path))
+                            .fix_width(260.),
+                    ),
+                },
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "↖",
+                AppState::watermark,
+                |watermark| watermark.position == WatermarkPosition::TopLeft,
+                |watermark| watermark.position = WatermarkPosition::TopLeft,
+            ))
+            .with_child(option_button(
+                "↗",
+                AppState::watermark,
+                |watermark| watermark.position == WatermarkPosition::TopRight,
+                |watermark| watermark.position = WatermarkPosition::TopRight,
+            ))
+            .with_child(option_button(
+                "↙",
+                AppState::watermark,
+                |watermark| watermark.position == WatermarkPosition::BottomLeft,
+                |watermark| watermark.position = WatermarkPosition::BottomLeft,
+            ))
+            .with_child(option_button(
+                "↘",
+                AppState::watermark,
+                |watermark| watermark.position == WatermarkPosition::BottomRight,
+                |watermark| watermark.position = WatermarkPosition::BottomRight,
+            ))
+            .with_child(option_button(
+                "Center",
+                AppState::watermark,
+                |watermark| watermark.position == WatermarkPosition::Center,
+                |watermark| watermark.position = WatermarkPosition::Center,
+            ))
+            .with_default_spacer()
+            .with_child(Label::new("margin").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::watermark.then(WatermarkSettings::margin),
+                5.,
+                (0., 200.),
+                |data: &AppState, _env| format!("{}px", data.watermark.margin),
+            ))
+            .with_child(Label::new("opacity").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::watermark.then(WatermarkSettings::opacity),
+                10.,
+                (10., 100.),
+                |data: &AppState, _env| format!("{}%", data.watermark.opacity),
+            ))
+            .with_child(Label::new("size").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::watermark.then(WatermarkSettings::size),
+                2.,
+                (2., 100.),
+                |data: &AppState, _env| match data.watermark.kind {
+                    WatermarkKind::Text => format!("{}px", data.watermark.size),
+                    WatermarkKind::Image => format!("{}% wide", data.watermark.size),
+                },
+            )),
+        Label::new(""),
+    );
+
+    let export_scale_button = Either::new(
+        |data: &AppState, _env| data.crop_screenshot_enabled == false && data.rename_file_enabled == false,
+        Button::from_label(Label::new(|data: &AppState, _env: &_| match data.export_scale.mode {
+            ScaleMode::Original => "Export size".to_string(),
+            ScaleMode::Percent => format!("Export size [{}%]", data.export_scale.value),
+            ScaleMode::Width => format!("Export size [{}px wide]", data.export_scale.value),
+            ScaleMode::Height => format!("Export size [{}px high]", data.export_scale.value),
+        }))
+        .on_click(move |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            data.export_scale_editing = !data.export_scale_editing;
+        }),
+        Label::new(""),
+    );
+
+    let export_scale_bar = Either::new(
+        |data: &AppState, _env| {
+            data.export_scale_editing
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Flex::row()
+            .with_child(option_button(
+                "Original",
+                AppState::export_scale,
+                |scale| scale.mode == ScaleMode::Original,
+                |scale| scale.mode = ScaleMode::Original,
+            ))
+            .with_child(option_button(
+                "%",
+                AppState::export_scale,
+                |scale| scale.mode == ScaleMode::Percent,
+                |scale| {
+                    if scale.mode != ScaleMode::Percent {
+                        scale.mode = ScaleMode::Percent;
+                        scale.value = 50.;
+                    }
+                },
+            ))
+            .with_child(option_button(
+                "Width",
+                AppState::export_scale,
+                |scale| scale.mode == ScaleMode::Width,
+                |scale| {
+                    if scale.mode != ScaleMode::Width {
+                        scale.mode = ScaleMode::Width;
+                        scale.value = 1280.;
+                    }
+                },
+            ))
+            .with_child(option_button(
+                "Height",
+                AppState::export_scale,
+                |scale| scale.mode == ScaleMode::Height,
+                |scale| {
+                    if scale.mode != ScaleMode::Height {
+                        scale.mode = ScaleMode::Height;
+                        scale.value = 720.;
+                    }
+                },
+            ))
+            .with_child(Either::new(
+                |data: &AppState, _env| data.export_scale.mode != ScaleMode::Original,
+                TextBox::new()
+                    .with_formatter(ParseFormatter::<f64>::new())
+                    .validate_while_editing(false)
+                    .lens(AppState::export_scale.map(
+                        |scale: &ExportScale| scale.value,
+                        |scale: &mut ExportScale, value: f64| scale.value = scale.clamp_value(value),
+                    ))
+                    .fix_width(70.),
+                Label::new(""),
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Lanczos3",
+                AppState::export_scale,
+                |scale| scale.filter == ResampleFilter::Lanczos3,
+                |scale| scale.filter = ResampleFilter::Lanczos3,
+            ))
+            .with_child(option_button(
+                "Triangle",
+                AppState::export_scale,
+                |scale| scale.filter == ResampleFilter::Triangle,
+                |scale| scale.filter = ResampleFilter::Triangle,
+            ))
+            .with_child(option_button(
+                "Nearest",
+                AppState::export_scale,
+                |scale| scale.filter == ResampleFilter::Nearest,
+                |scale| scale.filter = ResampleFilter::Nearest,
+            )),
+        Label::new(""),
+    );
+
+    let beautify_button = Either::new(
+        |data: &AppState, _env| data.crop_screenshot_enabled == false && data.rename_file_enabled == false,
+        Button::from_label(Label::new(|data: &AppState, _env: &_| {
+            if data.beautify.enabled {
+                "Beautify [On]"
+            } else {
+                "Beautify"
+            }
+        }))
+        .on_click(move |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            data.beautify_editing = !data.beautify_editing;
+        }),
+        Label::new(""),
+    );
+
+    let beautify_bar = Either::new(
+        |data: &AppState, _env| {
+            data.beautify_editing
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Flex::row()
+            .with_child(option_button(
+                "On",
+                AppState::beautify,
+                |beautify| beautify.enabled,
+                |beautify| beautify.enabled = !beautify.enabled,
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Transparent",
+                AppState::beautify,
+                |beautify| beautify.background == BeautifyBackground::Transparent,
+                |beautify| beautify.background = BeautifyBackground::Transparent,
+            ))
+            .with_child(option_button(
+                "White",
+                AppState::beautify,
+                |beautify| beautify.background == BeautifyBackground::White,
+                |beautify| beautify.background = BeautifyBackground::White,
+            ))
+            .with_child(option_button(
+                "Dark",
+                AppState::beautify,
+                |beautify| beautify.background == BeautifyBackground::Dark,
+                |beautify| beautify.background = BeautifyBackground::Dark,
+            ))
+            .with_child(option_button(
+                "Sunset",
+                AppState::beautify,
+                |beautify| beautify.background == BeautifyBackground::Sunset,
+                |beautify| beautify.background = BeautifyBackground::Sunset,
+            ))
+            .with_child(option_button(
+                "Ocean",
+                AppState::beautify,
+                |beautify| beautify.background == BeautifyBackground::Ocean,
+                |beautify| beautify.background = BeautifyBackground::Ocean,
+            ))
+            .with_default_spacer()
+            .with_child(Label::new("padding").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::beautify.then(Beautify::padding),
+                8.,
+                (0., 256.),
+                |data: &AppState, _env| format!("{}px", data.beautify.padding),
+            ))
+            .with_child(Label::new("corners").with_text_color(Color::BLACK))
+            .with_child(stepper(
+                AppState::beautify.then(Beautify::corner_radius),
+                4.,
+                (0., 64.),
+                |data: &AppState, _env| format!("{}px", data.beautify.corner_radius),
+            ))
+            .with_default_spacer()
+            .with_child(option_button(
+                "Shadow",
+                AppState::beautify,
+                |beautify| beautify.shadow,
+                |beautify| beautify.shadow = !beautify.shadow,
+            )),
+        Label::new(""),
+    );
+
+    let encode_bar = Either::new(
+        |data: &AppState, _env| {
+            (data.state == State::ScreenTaken(ImageModified::Savable) || data.state == State::Start)
+                && matches!(data.extension.trim_start_matches("."), "webp" | "avif")
+        },
+        Either::new(
+            |data: &AppState, _env| data.extension.trim_start_matches(".") == "webp",
+            Flex::row()
+                .with_child(Label::new("WebP").with_text_color(Color::BLACK))
+                .with_default_spacer()
+                .with_child(option_button(
+                    "Lossless",
+                    AppState::encode,
+                    |encode| encode.webp_lossless,
+                    |encode| encode.webp_lossless = true,
+                ))
+                .with_child(option_button(
+                    "Lossy",
+                    AppState::encode,
+                    |encode| !encode.webp_lossless,
+                    |encode| encode.webp_lossless = false,
+                ))
+                .with_default_spacer()
+                .with_child(Either::new(
+                    |data: &AppState, _env| !data.encode.webp_lossless,
+                    Flex::row()
+                        .with_child(Label::new("quality").with_text_color(Color::BLACK))
+                        .with_child(stepper(
+                            AppState::encode.then(EncodeSettings::webp_quality),
+                            5.,
+                            ENCODE_QUALITY_RANGE,
+                            |data: &AppState, _env| format!("{}", data.encode.webp_quality),
+                        )),
+                    Label::new(""),
+                )),
+            Flex::row()
+                .with_child(Label::new("AVIF").with_text_color(Color::BLACK))
+                .with_default_spacer()
+                .with_child(Label::new("quality").with_text_color(Color::BLACK))
+                .with_child(stepper(
+                    AppState::encode.then(EncodeSettings::avif_quality),
+                    5.,
+                    ENCODE_QUALITY_RANGE,
+                    |data: &AppState, _env| format!("{}", data.encode.avif_quality),
+                ))
+                .with_default_spacer()
+                .with_child(Label::new("speed").with_text_color(Color::BLACK))
+                .with_child(stepper(
+                    AppState::encode.then(EncodeSettings::avif_speed),
+                    1.,
+                    (1., 10.),
+                    |data: &AppState, _env| format!("{}", data.encode.avif_speed),
+                )),
+        ),
+        Label::new(""),
+    );
+
+    let stamps_bar = Either::new(
+        |data: &AppState, _env| {
+            data.stamps_opened == true
+                && data.state == State::ScreenTaken(ImageModified::NotSavable)
+        },
+        build_stamps_picker(&stamps),
+        Label::new(""),
+    );
+
+    let text_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        Button::from_label(Label::new("Text")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                data.text_field_zstack = true;
+                ctx.submit_command(sys_cmd::SHOW_ALL);
+            },
+        ),
+        Label::new(""),
+    );
+
+    let colors_button = Either::new(
+        |data: &AppState, _env| data.state == State::ScreenTaken(ImageModified::NotSavable),
+        ColoredButton::from_label(Label::new("Color"))
+            .with_color(Color::PURPLE)
+            .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                if data.colors_window_opened.is_none() {
+                    let mut init_pos = ctx.to_screen(Point::new(1., ctx.size().height - 1.));
+                    init_pos.y += 5.;
+                    let wd = WindowDesc::new(build_colors_window(*ZSTACK_ID))
+                        .title(WINDOW_TITLE)
+                        .set_always_on_top(true)
+                        .show_titlebar(false)
+                        .set_window_state(WindowState::Restored)
+                        .window_size((330., 470.))
+                        .set_position(init_pos)
+                        .resizable(false)
+                        .transparent(false);
+                    data.colors_window_opened = Some(wd.id);
+                    ctx.new_window(wd);
+                } else {
+                    ctx.get_external_handle()
+                        .submit_command(
+                            sys_cmd::CLOSE_WINDOW,
+                            (),
+                            Window(data.colors_window_opened.unwrap()),
+                        )
+                        .unwrap();
+                    data.colors_window_opened = None;
+                }
+            }),
+        Label::new(""),
+    );
+
+    let remove_over_img = Either::new(
+        |data: &AppState, _env| {
+            data.state == State::ScreenTaken(ImageModified::Savable)
+                && data.crop_screenshot_enabled == false
+                && data.rename_file_enabled == false
+        },
+        Button::from_label(Label::new("❌")).on_click(
+            move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                ctx.submit_command(
+                    SHOW_OVER_IMG
+                        .with((OverImages::Remove, None))
+                        .to(Target::Widget(*ZSTACK_ID)),
+                );
+                data.state = State::ScreenTaken(ImageModified::NotSavable);
+                data.text_field = "".to_string();
+                data.text_editing = false;
+                data.spotlight_editing = false;
+                data.callout_editing = false;
+            },
+        ),
+        Label::new(""),
+    );
+    let path_button = Either::new(
+        |data: &AppState, _env| {
             data.state == State::ScreenTaken(ImageModified::Savable) || data.state == State::Start
         },
         Button::from_label(Label::new("path")).on_click(
@@ -1172,6 +2384,12 @@ fn build_root_widget() -> impl Widget<AppState> {
                 .with_color(Color::rgb(0., 120. / 256., 0.))
                 .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
                     let (base_path, name) = file_name(data.name.clone(), data.base_path.clone());
+                    data.history.push_back(format!(
+                        "{}{}.{}",
+                        base_path,
+                        name,
+                        data.extension.trim_start_matches(".")
+                    ));
 
                     data.alert
                         .show_alert("The image has been saved on the disk!");
@@ -1189,6 +2407,10 @@ fn build_root_widget() -> impl Widget<AppState> {
 
                     data.state = State::ScreenTaken(ImageModified::NotSavable);
                     data.rename_file_enabled = false;
+                    data.text_field = "".to_string();
+                    data.text_editing = false;
+                    data.spotlight_editing = false;
+                    data.callout_editing = false;
                 }),
             ColoredButton::from_label(Label::new("Back").with_text_color(Color::BLACK))
                 .with_color(Color::rgb(0.8, 0.8, 0.))
@@ -1240,9 +2462,41 @@ fn build_root_widget() -> impl Widget<AppState> {
         .with_default_spacer()
         .with_child(highlighter_button)
         .with_default_spacer()
+        .with_child(redact_button)
+        .with_default_spacer()
+        .with_child(marker_button)
+        .with_default_spacer()
+        .with_child(stroke_button)
+        .with_default_spacer()
+        .with_child(stroke_rect_button)
+        .with_default_spacer()
+        .with_child(spotlight_button)
+        .with_default_spacer()
+        .with_child(spotlight_bar)
+        .with_default_spacer()
+        .with_child(callout_button)
+        .with_default_spacer()
+        .with_child(callout_bar)
+        .with_default_spacer()
+        .with_child(measure_line_button)
+        .with_default_spacer()
+        .with_child(measure_box_button)
+        .with_default_spacer()
+        .with_child(blend_bar)
+        .with_default_spacer()
+        .with_child(stamps_button)
+        .with_default_spacer()
         .with_child(text_button)
         .with_default_spacer()
         .with_child(colors_button)
+        .with_default_spacer()
+        .with_child(adjustments_button)
+        .with_default_spacer()
+        .with_child(watermark_button)
+        .with_default_spacer()
+        .with_child(export_scale_button)
+        .with_default_spacer()
+        .with_child(beautify_button)
         .with_spacer(40.)
         .with_child(text_field)
         .with_default_spacer()
@@ -1293,23 +2547,28 @@ fn build_root_widget() -> impl Widget<AppState> {
         .with_child(screen_selector);
 
     let alert_row = Flex::row()
-        .with_child(druid::widget::Either::new(
-            |data: &AppState, _| data.alert.alert_visible,
-            alert_widget(),
-            Label::new(""),
-        ))
+        .with_child(alert_widget(800.0).lens(AppState::alert))
         .center();
 
     let scroll = Scroll::new(
         Flex::column()
             .with_default_spacer()
             .with_child(Flex::row().with_child(buttons_bar))
+            .with_child(Flex::row().with_child(style_bar))
+            .with_child(Flex::row().with_child(stamps_bar))
+            .with_child(Flex::row().with_child(zoom_bar))
+            .with_child(Flex::row().with_child(adjustments_bar))
+            .with_child(Flex::row().with_child(watermark_bar))
+            .with_child(Flex::row().with_child(export_scale_bar))
+            .with_child(Flex::row().with_child(beautify_bar))
+            .with_child(Flex::row().with_child(encode_bar))
             .with_default_spacer()
             .with_child(Flex::row().with_child(alert_row))
             .with_default_spacer()
             .with_child(spaced_zstack),
     )
-    .vertical();
+    .vertical()
+    .controller(SettingsController::default());
     let layout = scroll.background(Color::WHITE).expand().padding(5.);
     layout
 }
@@ -1329,9 +2588,45 @@ pub fn set_path<T: Data>() -> MenuItem<T> {
         .command(commands::SHOW_OPEN_PANEL.with(FileDialogOptions::default().select_directories()))
 }
 
+pub fn save_project<T: Data>() -> MenuItem<T> {
+    let project = FileSpec::new("Screen Grabber project", &[PROJECT_EXTENSION]);
+    MenuItem::new(LocalizedString::new("Save Project..."))
+        .command(commands::SHOW_SAVE_PANEL.with(
+            FileDialogOptions::default()
+                .allowed_types(vec![project])
+                .default_type(project)
+                .accept_command(SAVE_PROJECT),
+        ))
+}
+
+pub fn open_project<T: Data>() -> MenuItem<T> {
+    let project = FileSpec::new("Screen Grabber project", &[PROJECT_EXTENSION]);
+    MenuItem::new(LocalizedString::new("Open Project..."))
+        .command(commands::SHOW_OPEN_PANEL.with(
+            FileDialogOptions::default()
+                .allowed_types(vec![project])
+                .accept_command(OPEN_PROJECT),
+        ))
+}
+
+pub fn combine_images<T: Data>() -> MenuItem<T> {
+    MenuItem::new(LocalizedString::new("Combine Images...")).command(SHOW_STITCH)
+}
+
+pub fn compare_images<T: Data>() -> MenuItem<T> {
+    MenuItem::new(LocalizedString::new("Compare Images...")).command(SHOW_COMPARE)
+}
+
 fn make_menu(_window: Option<WindowId>, _data: &AppState, _env: &Env) -> Menu<AppState> {
     let base = Menu::empty();
-    base.entry(Menu::new(LocalizedString::new("Edit")).entry(druid::platform_menus::common::copy()))
+    base.entry(
+        Menu::new(LocalizedString::new("File"))
+            .entry(open_project())
+            .entry(save_project())
+            .entry(combine_images())
+            .entry(compare_images()),
+    )
+    .entry(Menu::new(LocalizedString::new("Edit")).entry(druid::platform_menus::common::copy()))
         .entry(
             Menu::new(LocalizedString::new("Settings"))
                 .entry(show_about())
@@ -1342,22 +2637,89 @@ fn make_menu(_window: Option<WindowId>, _data: &AppState, _env: &Env) -> Menu<Ap
 
 fn build_colors_window(zstack_id_param: WidgetId) -> impl Widget<AppState> {
     let none = create_color_button(None, zstack_id_param);
-    let green = create_color_button(Some(Color::GREEN), zstack_id_param);
-    let red = create_color_button(Some(Color::RED), zstack_id_param);
-    let black = create_color_button(Some(Color::BLACK), zstack_id_param);
-    let white = create_color_button(Some(Color::WHITE), zstack_id_param);
-    let aqua = create_color_button(Some(Color::AQUA), zstack_id_param);
-    let gray = create_color_button(Some(Color::GRAY), zstack_id_param);
-    let blue = create_color_button(Some(Color::BLUE), zstack_id_param);
-    let fuchsia = create_color_button(Some(Color::FUCHSIA), zstack_id_param);
-    let lime = create_color_button(Some(Color::LIME), zstack_id_param);
-    let maroon = create_color_button(Some(Color::MAROON), zstack_id_param);
-    let navy = create_color_button(Some(Color::NAVY), zstack_id_param);
-    let olive = create_color_button(Some(Color::OLIVE), zstack_id_param);
-    let purple = create_color_button(Some(Color::PURPLE), zstack_id_param);
-    let teal = create_color_button(Some(Color::TEAL), zstack_id_param);
-    let yellow = create_color_button(Some(Color::YELLOW), zstack_id_param);
-    let silver = create_color_button(Some(Color::SILVER), zstack_id_param);
+
+    let picker = Flex::row()
+        .with_child(SaturationValueSquare)
+        .with_default_spacer()
+        .with_child(HueBar)
+        .lens(AppState::color_picker);
+
+    let preview = Painter::new(|ctx, data: &ColorPickerState, _env| {
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &data.color());
+    })
+    .fix_size(40., 24.)
+    .border(Color::WHITE, 1.);
+
+    let hex_row = Flex::row()
+        .with_child(preview)
+        .with_default_spacer()
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::<HexColor>::new())
+                .validate_while_editing(false)
+                .lens(ColorPickerState::hex_lens())
+                .fix_width(90.),
+        )
+        .lens(AppState::color_picker);
+
+    let rgb_row = Flex::row()
+        .with_child(color_value_box("R", ColorPickerState::rgb_lens(0)))
+        .with_child(color_value_box("G", ColorPickerState::rgb_lens(1)))
+        .with_child(color_value_box("B", ColorPickerState::rgb_lens(2)))
+        .lens(AppState::color_picker);
+
+    let hsv_row = Flex::row()
+        .with_child(color_value_box("H", ColorPickerState::hue.map(
+            |hue: &f64| hue.round(),
+            |hue: &mut f64, new_hue: f64| *hue = new_hue.clamp(0., 360.),
+        )))
+        .with_child(color_value_box(
+            "S",
+            ColorPickerState::percent_lens(|data| data.saturation, |data, s| data.saturation = s),
+        ))
+        .with_child(color_value_box(
+            "V",
+            ColorPickerState::percent_lens(|data| data.value, |data, v| data.value = v),
+        ))
+        .lens(AppState::color_picker);
+
+    let recent_colors = ViewSwitcher::new(
+        |data: &AppState, _env| data.color_picker.recent.clone(),
+        |recent, _data, _env| {
+            let mut flex = Flex::row();
+            for color in recent.iter().cloned() {
+                flex.add_child(
+                    ColoredButton::from_label(Label::new(" "))
+                        .with_color(color)
+                        .on_click(move |_ctx, data: &mut AppState, _env| {
+                            data.color_picker.set_color(color);
+                        }),
+                );
+                flex.add_spacer(4.);
+            }
+            Box::new(flex)
+        },
+    );
+
+    let eyedropper = Button::new("Eyedropper").on_click(move |ctx: &mut EventCtx, _data: &mut AppState, _env: &Env| {
+        ctx.get_external_handle()
+            .submit_command(START_EYEDROPPER, (), Target::Widget(zstack_id_param))
+            .unwrap();
+    });
+
+    let apply = ColoredButton::from_label(Label::new("Apply"))
+        .with_color(Color::rgb(0., 120. / 256., 0.))
+        .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            let color = data.color_picker.color();
+            ctx.get_external_handle()
+                .submit_command(UPDATE_COLOR, (Some(color), None), Target::Widget(zstack_id_param))
+                .unwrap();
+            data.color = Some(color);
+            data.color_picker.add_recent(color);
+            data.colors_window_opened = None;
+            ctx.window().close();
+        });
 
     let label = Label::new("Transparency:").with_text_color(Color::WHITE);
     let alpha_slider = CustomSlider::new()
@@ -1373,59 +2735,23 @@ fn build_colors_window(zstack_id_param: WidgetId) -> impl Widget<AppState> {
         .with_default_spacer()
         .with_child(none)
         .with_default_spacer()
-        .with_child(
-            Flex::row()
-                .with_default_spacer()
-                .with_child(green)
-                .with_default_spacer()
-                .with_child(red)
-                .with_default_spacer()
-                .with_child(black)
-                .with_default_spacer()
-                .with_child(white)
-                .with_default_spacer(),
-        )
+        .with_child(picker)
         .with_default_spacer()
-        .with_child(
-            Flex::row()
-                .with_default_spacer()
-                .with_child(aqua)
-                .with_default_spacer()
-                .with_child(gray)
-                .with_default_spacer()
-                .with_child(blue)
-                .with_default_spacer()
-                .with_child(fuchsia)
-                .with_default_spacer(),
-        )
+        .with_child(hex_row)
         .with_default_spacer()
-        .with_child(
-            Flex::row()
-                .with_default_spacer()
-                .with_child(lime)
-                .with_default_spacer()
-                .with_child(maroon)
-                .with_default_spacer()
-                .with_child(navy)
-                .with_default_spacer()
-                .with_child(olive)
-                .with_default_spacer(),
-        )
+        .with_child(rgb_row)
+        .with_child(hsv_row)
+        .with_default_spacer()
+        .with_child(Label::new("Recent Colors:").with_text_color(Color::WHITE))
+        .with_child(recent_colors)
         .with_default_spacer()
         .with_child(
             Flex::row()
+                .with_child(eyedropper)
                 .with_default_spacer()
-                .with_child(purple)
-                .with_default_spacer()
-                .with_child(teal)
-                .with_default_spacer()
-                .with_child(yellow)
-                .with_default_spacer()
-                .with_child(silver)
-                .with_default_spacer(),
+                .with_child(apply),
         )
         .with_default_spacer()
-        .with_default_spacer()
         .with_child(Flex::row().with_child(label))
         .with_child(Flex::row().with_child(alpha_slider))
         .background(Color::BLACK.with_alpha(0.3));
@@ -1433,6 +2759,32 @@ fn build_colors_window(zstack_id_param: WidgetId) -> impl Widget<AppState> {
     Align::centered(flex)
 }
 
+/// A labeled input box for a single component of the color, it is updated when Enter is pressed.
+fn color_value_box<T: Data + std::fmt::Display + FromStr + 'static>(
+    name: &str,
+    lens: impl Lens<ColorPickerState, T> + 'static,
+) -> impl Widget<ColorPickerState>
+where
+    <T as FromStr>::Err: std::error::Error + 'static,
+{
+    Flex::row()
+        .with_child(Label::new(name).with_text_color(Color::WHITE))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::<T>::new())
+                .validate_while_editing(false)
+                .lens(lens)
+                .fix_width(50.),
+        )
+        .padding(2.)
+}
+
+fn labeled_checkbox(text: &str) -> impl Widget<bool> {
+    Flex::row()
+        .with_child(Checkbox::new(""))
+        .with_child(Label::new(text).with_text_color(Color::BLACK))
+}
+
 fn create_color_button(
     color: Option<Color>,
     zstack_id_param: WidgetId,
@@ -1447,6 +2799,616 @@ fn create_color_button(
             ctx.window().close();
         })
 }
+/**
+* This function loads the images found in the stamps directory, sorted by name: the ones that
+* can't be decoded are skipped and their names are returned to be reported.
+*/
+fn load_stamps(dir: &str) -> (Vec<(String, DynamicImage)>, Vec<String>) {
+    let mut paths: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && image::ImageFormat::from_path(path).is_ok())
+            .collect(),
+        Err(_) => return (Vec::new(), Vec::new()),
+    };
+    paths.sort();
+
+    let mut stamps = Vec::new();
+    let mut unreadable = Vec::new();
+    for path in paths {
+        let name = path.to_string_lossy().replace("\\", "/");
+        match Reader::open(&path).ok().and_then(|reader| reader.decode().ok()) {
+            Some(stamp) => stamps.push((name, DynamicImage::ImageRgba8(stamp.into_rgba8()))),
+            None => unreadable.push(name),
+        }
+    }
+    (stamps, unreadable)
+}
+
+/**
+* This function creates a button that changes the settings reached through the lens, the selected
+* option is written between brackets.
+*/
+fn option_button<S: Data>(
+    name: &'static str,
+    lens: impl Lens<AppState, S> + 'static,
+    selected: impl Fn(&S) -> bool + 'static,
+    update: impl Fn(&mut S) + 'static,
+) -> impl Widget<AppState> {
+    Button::from_label(Label::new(move |data: &S, _env: &_| {
+        if selected(data) {
+            format!("[{}]", name)
+        } else {
+            name.to_string()
+        }
+    }))
+    .on_click(move |_ctx: &mut EventCtx, data: &mut S, _env: &Env| update(data))
+    .lens(lens)
+}
+
+/**
+* This function creates the "-" and "+" buttons that change by `step` a value of the settings
+* reached through the lens, keeping it between `min` and `max`, with the value shown between them.
+*/
+fn stepper(
+    lens: impl Lens<AppState, f64> + Clone + 'static,
+    step: f64,
+    (min, max): (f64, f64),
+    text: impl Fn(&AppState, &Env) -> String + 'static,
+) -> impl Widget<AppState> {
+    let step_button = move |name: &str, step: f64, lens| {
+        Button::new(name)
+            .on_click(move |_ctx: &mut EventCtx, value: &mut f64, _env: &Env| {
+                *value = (*value + step).max(min).min(max)
+            })
+            .lens(lens)
+    };
+    Flex::row()
+        .with_child(step_button("-", -step, lens.clone()))
+        .with_child(Label::dynamic(text).with_text_color(Color::BLACK))
+        .with_child(step_button("+", step, lens))
+}
+
+/// Sends the settings chosen in the bars to the widgets that use them every time they change,
+/// the export settings are also written on the disk once an edit is committed, instead of on
+/// every keystroke.
+#[derive(Default)]
+struct SettingsController {
+    /// the settings as they were last written on the disk
+    saved: Option<(WatermarkSettings, ExportScale, Beautify, EncodeSettings)>,
+}
+
+impl SettingsController {
+    /**
+    * This function writes the settings changed since the last save, the failures are shown in the
+    * alert.
+    */
+    fn save(&mut self, data: &mut AppState) {
+        let (watermark, export_scale, beautify, encode) = match &mut self.saved {
+            Some(saved) => saved,
+            None => return,
+        };
+        let mut errors = Vec::<String>::new();
+        if !watermark.same(&data.watermark) {
+            verify_exists_dir(BASE_PATH_SETTINGS);
+            match write_to_file(PATH_WATERMARK, &data.watermark) {
+                Ok(()) => *watermark = data.watermark.clone(),
+                Err(e) => errors.push(format!("the watermark settings ({})", e)),
+            }
+        }
+        if !export_scale.same(&data.export_scale) {
+            verify_exists_dir(BASE_PATH_SETTINGS);
+            match write_to_file(PATH_EXPORT_SCALE, &data.export_scale) {
+                Ok(()) => *export_scale = data.export_scale,
+                Err(e) => errors.push(format!("the export size ({})", e)),
+            }
+        }
+        if !beautify.same(&data.beautify) {
+            verify_exists_dir(BASE_PATH_SETTINGS);
+            match write_to_file(PATH_BEAUTIFY, &data.beautify) {
+                Ok(()) => *beautify = data.beautify,
+                Err(e) => errors.push(format!("the beautify settings ({})", e)),
+            }
+        }
+        if !encode.same(&data.encode) {
+            verify_exists_dir(BASE_PATH_SETTINGS);
+            match write_to_file(PATH_ENCODE, &data.encode) {
+                Ok(()) => *encode = data.encode,
+                Err(e) => errors.push(format!("the quality settings ({})", e)),
+            }
+        }
+        if !errors.is_empty() {
+            data.alert.show_alert(&format!("Error saving {}", errors.join(", ")));
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SettingsController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        child.event(ctx, event, data, env);
+        // an edit is committed when a slider or a button is released, when a text box loses the
+        // focus or when the window is closed
+        match event {
+            Event::MouseUp(_) | Event::WindowCloseRequested => self.save(data),
+            Event::KeyUp(key) if key.key == KbKey::Enter || key.key == KbKey::Tab => self.save(data),
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, child: &mut W, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.saved = Some((data.watermark.clone(), data.export_scale, data.beautify, data.encode));
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, child: &mut W, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if !old_data.watermark.same(&data.watermark) {
+            ctx.submit_command(SET_WATERMARK.with(data.watermark.clone()).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.export_scale.same(&data.export_scale) {
+            ctx.submit_command(SET_EXPORT_SCALE.with(data.export_scale).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.beautify.same(&data.beautify) {
+            ctx.submit_command(SET_BEAUTIFY.with(data.beautify).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.encode.same(&data.encode) {
+            ctx.submit_command(SET_ENCODE.with(data.encode).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.annotation_style.same(&data.annotation_style) {
+            ctx.submit_command(SET_ANNOTATION_STYLE.with(data.annotation_style).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.blend_mode.same(&data.blend_mode) {
+            ctx.submit_command(SET_BLEND_MODE.with(data.blend_mode).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.spotlight.same(&data.spotlight) {
+            ctx.submit_command(SET_SPOTLIGHT.with(data.spotlight).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.adjustments.same(&data.adjustments) {
+            ctx.submit_command(SET_ADJUSTMENTS.with(data.adjustments).to(Target::Widget(*ZSTACK_ID)));
+        }
+        if !old_data.crop_aspect.same(&data.crop_aspect) {
+            ctx.submit_command(SET_CROP_ASPECT.with(data.crop_aspect));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/**
+* This function creates a button that rotates or mirrors the whole screenshot, with everything
+* drawn on it.
+*/
+fn orientation_button(name: &'static str, orientation: Orientation) -> impl Widget<AppState> {
+    Button::from_label(Label::new(name)).on_click(
+        move |ctx: &mut EventCtx, _data: &mut AppState, _env: &Env| {
+            ctx.submit_command(ORIENT_SCREENSHOT.with(orientation).to(Target::Widget(*ZSTACK_ID)));
+        },
+    )
+}
+
+/**
+* This function creates a button that shows the screenshot fitting the window, with `None`, or
+* with the given layout points for every pixel.
+*/
+fn zoom_button(name: &'static str, zoom: Option<f64>) -> impl Widget<AppState> {
+    Button::from_label(Label::new(name)).on_click(
+        move |ctx: &mut EventCtx, _data: &mut AppState, _env: &Env| {
+            ctx.submit_command(SET_ZOOM.with(zoom).to(Target::Widget(*ZSTACK_ID)));
+        },
+    )
+}
+
+fn build_stamps_picker(stamps: &[(String, DynamicImage)]) -> impl Widget<AppState> {
+    let mut row = Flex::row();
+    if stamps.is_empty() {
+        row.add_child(
+            Label::new(format!("Add some images to {} to use them as stamps", BASE_PATH_STAMPS))
+                .with_text_color(Color::BLACK),
+        );
+    }
+    for (i, (path, stamp)) in stamps.iter().enumerate() {
+        let thumbnail = stamp
+            .thumbnail(STAMP_THUMBNAIL_SIZE, STAMP_THUMBNAIL_SIZE)
+            .into_rgba8();
+        let name = Path::new(path)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        row.add_child(
+            Flex::column()
+                .with_child(
+                    Image::new(ImageBuf::from_raw(
+                        Arc::<[u8]>::from(thumbnail.as_raw().as_slice()),
+                        ImageFormat::RgbaSeparate,
+                        thumbnail.width() as usize,
+                        thumbnail.height() as usize,
+                    ))
+                    .fix_size(STAMP_THUMBNAIL_SIZE as f64, STAMP_THUMBNAIL_SIZE as f64),
+                )
+                .with_child(Label::new(name).with_text_size(10.).with_text_color(Color::BLACK))
+                .padding(2.)
+                .border(Color::BLACK.with_alpha(0.6), 1.)
+                .on_click(move |ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                    ctx.submit_command(
+                        SHOW_OVER_IMG
+                            .with((OverImages::Stamp(i), None))
+                            .to(Target::Widget(*ZSTACK_ID)),
+                    );
+                    data.stamps_opened = false;
+                    data.state = State::ScreenTaken(ImageModified::Savable);
+                }),
+        );
+        row.add_default_spacer();
+    }
+    Scroll::new(row).horizontal()
+}
+
+/**
+* This function lists the images that can be combined: the ones saved in this session first, then
+* the other ones of the save directory, the most recent first.
+*/
+fn stitch_candidates(history: &Vector<String>, base_path: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = history
+        .iter()
+        .rev()
+        .filter(|path| Path::new(path).exists())
+        .cloned()
+        .collect();
+
+    let mut saved: Vec<(std::time::SystemTime, String)> = std::fs::read_dir(base_path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    let extension = entry
+                        .path()
+                        .extension()
+                        .map(|extension| extension.to_string_lossy().to_lowercase());
+                    matches!(extension.as_deref(), Some("png" | "jpg" | "jpeg" | "gif" | "webp"))
+                })
+                .map(|entry| {
+                    let modified = entry
+                        .metadata()
+                        .and_then(|metadata| metadata.modified())
+                        .unwrap_or(std::time::UNIX_EPOCH);
+                    (modified, format!("{}{}", base_path, entry.file_name().to_string_lossy()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    saved.sort_by(|a, b| b.0.cmp(&a.0));
+    for (_, path) in saved {
+        if !candidates.contains(&path) {
+            candidates.push(path);
+        }
+    }
+    candidates.truncate(STITCH_MAX_CANDIDATES);
+    candidates
+}
+
+/**
+* This function creates the window to combine several images into one: a click on an image adds it
+* after the chosen ones, or removes it.
+*/
+fn build_stitch_widget(candidates: &[String]) -> impl Widget<AppState> {
+    let mut images = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    if candidates.is_empty() {
+        images.add_child(
+            Label::new("There are no images to combine, take some screenshots first")
+                .with_text_color(Color::BLACK),
+        );
+    }
+    for row_candidates in candidates.chunks(STITCH_CANDIDATES_PER_ROW) {
+        let mut row = Flex::row();
+        for path in row_candidates {
+            let thumbnail = match image::open(path) {
+                Ok(img) => img
+                    .thumbnail(STITCH_THUMBNAIL_SIZE, STITCH_THUMBNAIL_SIZE)
+                    .into_rgba8(),
+                Err(_) => continue,
+            };
+            let name = Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let selected_path = path.clone();
+            let clicked_path = path.clone();
+            row.add_child(
+                Flex::column()
+                    .with_child(
+                        Image::new(ImageBuf::from_raw(
+                            Arc::<[u8]>::from(thumbnail.as_raw().as_slice()),
+                            ImageFormat::RgbaSeparate,
+                            thumbnail.width() as usize,
+                            thumbnail.height() as usize,
+                        ))
+                        .fix_size(STITCH_THUMBNAIL_SIZE as f64, STITCH_THUMBNAIL_SIZE as f64),
+                    )
+                    .with_child(
+                        Label::new(name)
+                            .with_text_size(10.)
+                            .with_text_color(Color::BLACK)
+                            .fix_width(STITCH_THUMBNAIL_SIZE as f64),
+                    )
+                    .with_child(
+                        Label::dynamic(move |data: &AppState, _env| {
+                            match data.stitch_selected.index_of(&selected_path) {
+                                Some(index) => format!("[{}]", index + 1),
+                                None => "".to_string(),
+                            }
+                        })
+                        .with_text_color(Color::BLACK),
+                    )
+                    .padding(2.)
+                    .border(Color::BLACK.with_alpha(0.6), 1.)
+                    .on_click(move |_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+                        match data.stitch_selected.index_of(&clicked_path) {
+                            Some(index) => {
+                                data.stitch_selected.remove(index);
+                            }
+                            None => data.stitch_selected.push_back(clicked_path.clone()),
+                        }
+                    }),
+            );
+            row.add_default_spacer();
+        }
+        images.add_child(row);
+        images.add_default_spacer();
+    }
+
+    let options = Flex::row()
+        .with_child(option_button(
+            "Horizontal",
+            AppState::stitch,
+            |stitch| stitch.layout == StitchLayout::Horizontal,
+            |stitch| stitch.layout = StitchLayout::Horizontal,
+        ))
+        .with_child(option_button(
+            "Vertical",
+            AppState::stitch,
+            |stitch| stitch.layout == StitchLayout::Vertical,
+            |stitch| stitch.layout = StitchLayout::Vertical,
+        ))
+        .with_child(option_button(
+            "Grid",
+            AppState::stitch,
+            |stitch| stitch.layout == StitchLayout::Grid,
+            |stitch| stitch.layout = StitchLayout::Grid,
+        ))
+        .with_child(stepper(
+            AppState::stitch.then(StitchSettings::columns),
+            1.,
+            (1., 10.),
+            |data: &AppState, _env| format!("{} columns", data.stitch.columns),
+        ));
+
+    let style = Flex::row()
+        .with_child(Label::new("spacing").with_text_color(Color::BLACK))
+        .with_child(stepper(
+            AppState::stitch.then(StitchSettings::spacing),
+            8.,
+            (0., 128.),
+            |data: &AppState, _env| format!("{}px", data.stitch.spacing),
+        ))
+        .with_default_spacer()
+        .with_child(option_button(
+            "White",
+            AppState::stitch,
+            |stitch| stitch.background == StitchBackground::White,
+            |stitch| stitch.background = StitchBackground::White,
+        ))
+        .with_child(option_button(
+            "Black",
+            AppState::stitch,
+            |stitch| stitch.background == StitchBackground::Black,
+            |stitch| stitch.background = StitchBackground::Black,
+        ))
+        .with_child(option_button(
+            "Transparent",
+            AppState::stitch,
+            |stitch| stitch.background == StitchBackground::Transparent,
+            |stitch| stitch.background = StitchBackground::Transparent,
+        ));
+
+    let export_button = ColoredButton::from_label(Label::new("Export..."))
+        .with_color(Color::rgb(0., 120. / 256., 0.))
+        .on_click(|ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            if data.stitch_selected.is_empty() {
+                data.stitch_alert.show_alert("Choose the images to combine!");
+                return;
+            }
+            let png = FileSpec::new("PNG image", &["png"]);
+            let jpg = FileSpec::new("JPG image", &["jpg", "jpeg"]);
+            let gif = FileSpec::new("GIF image", &["gif"]);
+            let webp = FileSpec::new("WebP image", &["webp"]);
+            let avif = FileSpec::new("AVIF image", &["avif"]);
+            // only the formats this build can write
+            let types = vec![png, jpg, gif, webp, avif]
+                .into_iter()
+                .filter(|spec| SAVE_FORMATS.iter().any(|(_, extension)| spec.extensions.contains(extension)))
+                .collect();
+            ctx.submit_command(commands::SHOW_SAVE_PANEL.with(
+                FileDialogOptions::default()
+                    .allowed_types(types)
+                    .default_type(png)
+                    .default_name("combined")
+                    .accept_command(SAVE_STITCH),
+            ));
+        });
+
+    let clear_button = Button::from_label(Label::new("Clear"))
+        .on_click(|_ctx: &mut EventCtx, data: &mut AppState, _env: &Env| {
+            data.stitch_selected.clear();
+        });
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_flex_child(Scroll::new(images).vertical().expand_width(), 1.)
+        .with_default_spacer()
+        .with_child(options)
+        .with_default_spacer()
+        .with_child(style)
+        .with_default_spacer()
+        .with_child(alert_widget(520.0).lens(AppState::stitch_alert))
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(export_button)
+                .with_default_spacer()
+                .with_child(clear_button)
+                .with_default_spacer()
+                .with_child(
+                    Label::dynamic(|data: &AppState, _env| {
+                        format!("{} images chosen", data.stitch_selected.len())
+                    })
+                    .with_text_color(Color::BLACK),
+                ),
+        )
+        .padding(10.)
+        .background(Color::WHITE)
+}
+
+/**
+* This function creates the window to compare an older and a newer image, the differences are
+* highlighted over the newer one.
+*/
+fn build_compare_widget() -> impl Widget<AppState> {
+    let images = FileSpec::new("Images", &["png", "jpg", "jpeg", "gif", "webp", "bmp"]);
+    let choose_row = Flex::row()
+        .with_child(Button::from_label(Label::new("Before...")).on_click(
+            move |ctx: &mut EventCtx, _data: &mut AppState, _env: &Env| {
+                ctx.submit_command(commands::SHOW_OPEN_PANEL.with(
+                    FileDialogOptions::default()
+                        .allowed_types(vec![images])
+                        .accept_command(COMPARE_BEFORE),
+                ));
+            },
+        ))
+        .with_child(
+            Label::dynamic(|data: &AppState, _env| file_name_of(&data.compare_before))
+                .with_text_color(Color::BLACK),
+        )
+        .with_default_spacer()
+        .with_child(Button::from_label(Label::new("After...")).on_click(
+            move |ctx: &mut EventCtx, _data: &mut AppState, _env: &Env| {
+                ctx.submit_command(commands::SHOW_OPEN_PANEL.with(
+                    FileDialogOptions::default()
+                        .allowed_types(vec![images])
+                        .accept_command(COMPARE_AFTER),
+                ));
+            },
+        ))
+        .with_child(
+            Label::dynamic(|data: &AppState, _env| file_name_of(&data.compare_after))
+                .with_text_color(Color::BLACK),
+        );
+
+    let options_row = Flex::row()
+        .with_child(option_button(
+            "Side by side",
+            AppState::compare,
+            |compare| compare.mode == CompareMode::SideBySide,
+            |compare| compare.mode = CompareMode::SideBySide,
+        ))
+        .with_child(option_button(
+            "Swipe",
+            AppState::compare,
+            |compare| compare.mode == CompareMode::Swipe,
+            |compare| compare.mode = CompareMode::Swipe,
+        ))
+        .with_child(option_button(
+            "Difference",
+            AppState::compare,
+            |compare| compare.mode == CompareMode::Difference,
+            |compare| compare.mode = CompareMode::Difference,
+        ))
+        .with_default_spacer()
+        .with_child(Label::new("tolerance").with_text_color(Color::BLACK))
+        .with_child(stepper(
+            AppState::compare.then(CompareSettings::tolerance),
+            1.,
+            (0., 100.),
+            |data: &AppState, _env| format!("{}%", data.compare.tolerance),
+        ))
+        .with_default_spacer()
+        .with_child(option_button(
+            "Highlight",
+            AppState::compare,
+            |compare| compare.highlight,
+            |compare| compare.highlight = !compare.highlight,
+        ));
+
+    let changed_label = Label::dynamic(|data: &AppState, _env| match data.compare_changed {
+        Some((changed, total)) => format!(
+            "{} changed pixels ({:.2}%)",
+            changed,
+            changed as f64 * 100. / total.max(1) as f64
+        ),
+        None => "Choose the two images to compare".to_string(),
+    })
+    .with_text_color(Color::BLACK);
+
+    let swipe_hint = Either::new(
+        |data: &AppState, _env| data.compare.mode == CompareMode::Swipe,
+        Label::new("Drag on the image to move the line")
+            .with_text_color(Color::BLACK.with_alpha(0.6)),
+        Label::new(""),
+    );
+
+    let compare_image = IdentityWrapper::wrap(
+        ScreenshotImage::new(ImageBuf::from_raw(
+            Arc::<[u8]>::from(Vec::from([0, 0, 0, 0]).as_slice()),
+            ImageFormat::RgbaSeparate,
+            1usize,
+            1usize,
+        ))
+        .controller(CompareController::new()),
+        *COMPARE_WIDGET_ID,
+    );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(choose_row)
+        .with_default_spacer()
+        .with_child(options_row)
+        .with_default_spacer()
+        .with_child(Flex::row().with_child(changed_label).with_default_spacer().with_child(swipe_hint))
+        .with_default_spacer()
+        .with_child(alert_widget(600.0).lens(AppState::compare_alert))
+        .with_default_spacer()
+        .with_flex_child(Scroll::new(compare_image).vertical().expand_width(), 1.)
+        .padding(10.)
+        .background(Color::WHITE)
+        .controller(CompareSettingsController)
+}
+
+/// Sends the compare settings to the compare view every time they change.
+struct CompareSettingsController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for CompareSettingsController {
+    fn update(&mut self, child: &mut W, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if !old_data.compare.same(&data.compare) {
+            ctx.submit_command(SET_COMPARE.with(data.compare).to(Target::Widget(*COMPARE_WIDGET_ID)));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/**
+* This function returns the name of the file at the given path, or a placeholder if no file has
+* been chosen.
+*/
+fn file_name_of(path: &str) -> String {
+    if path.is_empty() {
+        return "no image".to_string();
+    }
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
 /**
 * This function assigns a name and a file path to an image stored on the disk.
 */